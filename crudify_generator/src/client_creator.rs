@@ -0,0 +1,460 @@
+use crate::main_file_creator::{get_id_type, get_struct_fields_code, get_struct_properties, has_upsert};
+use crate::sql_creator::is_searchable;
+use crate::{Endpoint, InternalModel, InternalModels};
+
+/// The `lib.rs` of the `{name}_client` crate: the model structs of the server, an async client with one method per
+/// route and, behind the `blocking` feature, the same client on `reqwest::blocking`.
+pub fn create_client(models: &InternalModels) -> String {
+    let methods: String = models.iter().map(get_client_methods_code).collect();
+    let client = format!("{}\nimpl Client {{\n{}}}\n", CLIENT_CORE, methods);
+
+    format!(
+        "{}\n{}\n{}\n/// The client without an async runtime.\n#[cfg(feature = \"blocking\")]\npub mod blocking {{\nuse super::*;\n{}}}\n",
+        CLIENT_TYPES,
+        get_client_structs(models),
+        client,
+        to_blocking(&client)
+    )
+}
+
+/// The blocking client is the async one with `reqwest::blocking` and without the `.await`s.
+fn to_blocking(code: &str) -> String {
+    code.replace("async fn", "fn")
+        .replace(".await", "")
+        .replace("reqwest::Client", "reqwest::blocking::Client")
+        .replace("reqwest::RequestBuilder", "reqwest::blocking::RequestBuilder")
+        .replace("reqwest::Response", "reqwest::blocking::Response")
+}
+
+/// The same structs the server (de)serializes, with public fields.
+fn get_client_structs(models: &InternalModels) -> String {
+    let mut code = "".to_string();
+    for model in models {
+        let name = model.name.to_lowercase();
+        let (readable, writable, updatable) = get_struct_properties(model);
+        let mut structs = vec![(model.name.to_string(), readable)];
+        if model.has_endpoint(Endpoint::Create) {
+            structs.push((format!("Create{}", model.name), writable));
+        }
+        if model.has_endpoint(Endpoint::Update) {
+            structs.push((format!("Update{}", model.name), updatable));
+        }
+        for (struct_name, properties) in structs {
+            code.push_str(&format!(
+                "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\npub struct {} {{\n{}}}\n\n",
+                struct_name,
+                get_struct_fields_code(&name, &properties, false, false, false, "pub ")
+            ));
+        }
+    }
+    code
+}
+
+/// String ids are borrowed, the others are copied.
+fn get_id_param_type(model: &InternalModel) -> String {
+    match get_id_type(model).as_str() {
+        "String" => "&str".to_string(),
+        id_type => id_type.to_string(),
+    }
+}
+
+/// The methods of a model, named like the handlers they call. Versioned rows are changed with the `version` they are
+/// expected to be at.
+fn get_client_methods_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let id = get_id_param_type(model);
+    let (version_param, if_match) = if model.options.versioned {
+        ("version: i64, ", ".header(\"If-Match\", if_match(version))")
+    } else {
+        ("", "")
+    };
+    let mut code = "".to_string();
+
+    if model.has_endpoint(Endpoint::List) {
+        code.push_str(&format!(
+            r#"
+    /// `GET /api/{0}`, a page of the rows matching the `query`.
+    pub async fn list_{0}(&self, query: &ListQuery) -> Result<Page<{1}>, Error> {{
+        self.send(self.request(reqwest::Method::GET, &["api", "{0}"]).query(query.params())).await
+    }}
+
+    /// `GET /api/{0}/:id`
+    pub async fn get_{0}(&self, id: {2}) -> Result<{1}, Error> {{
+        self.send(self.request(reqwest::Method::GET, &["api", "{0}", &id.to_string()])).await
+    }}
+"#,
+            name, model.name, id
+        ));
+        if is_searchable(model) {
+            code.push_str(&format!(
+                r#"
+    /// `GET /api/{0}/search`, the best ranked rows for `q`.
+    pub async fn search_{0}(&self, q: &str, limit: Option<i64>, offset: Option<i64>) -> Result<Page<SearchHit<{1}>>, Error> {{
+        let mut params = vec![("q", q.to_string())];
+        params.extend(limit.map(|limit| ("limit", limit.to_string())));
+        params.extend(offset.map(|offset| ("offset", offset.to_string())));
+        self.send(self.request(reqwest::Method::GET, &["api", "{0}", "search"]).query(&params)).await
+    }}
+"#,
+                name, model.name
+            ));
+        }
+        if model.options.history {
+            code.push_str(&format!(
+                r#"
+    /// `GET /api/{0}/:id/history`, the changes of the row, oldest first.
+    pub async fn get_{0}_history(&self, id: {1}) -> Result<Vec<HistoryEntry>, Error> {{
+        self.send(self.request(reqwest::Method::GET, &["api", "{0}", &id.to_string(), "history"])).await
+    }}
+"#,
+                name, id
+            ));
+        }
+    }
+
+    if model.has_endpoint(Endpoint::Create) {
+        code.push_str(&format!(
+            r#"
+    /// `POST /api/{0}`
+    pub async fn create_{0}(&self, {0}: &Create{1}) -> Result<{1}, Error> {{
+        self.send(self.request(reqwest::Method::POST, &["api", "{0}"]).json({0})).await
+    }}
+"#,
+            name, model.name
+        ));
+    }
+    if has_upsert(model) {
+        code.push_str(&format!(
+            r#"
+    /// `PUT /api/{0}`, creates the row or updates the one with the same upsert key.
+    pub async fn upsert_{0}(&self, {0}: &Create{1}) -> Result<{1}, Error> {{
+        self.send(self.request(reqwest::Method::PUT, &["api", "{0}"]).json({0})).await
+    }}
+"#,
+            name, model.name
+        ));
+    }
+
+    if model.has_endpoint(Endpoint::Update) {
+        code.push_str(&format!(
+            r#"
+    /// `PUT /api/{0}/:id`
+    pub async fn update_{0}(&self, id: {2}, {3}{0}: &Update{1}) -> Result<{1}, Error> {{
+        self.send(self.request(reqwest::Method::PUT, &["api", "{0}", &id.to_string()]){4}.json({0})).await
+    }}
+
+    /// `PATCH /api/{0}/:id`, changes the properties of `patch` only.
+    pub async fn patch_{0}(&self, id: {2}, {3}patch: &serde_json::Value) -> Result<{1}, Error> {{
+        self.send(self.request(reqwest::Method::PATCH, &["api", "{0}", &id.to_string()]){4}.json(patch)).await
+    }}
+"#,
+            name, model.name, id, version_param, if_match
+        ));
+    }
+
+    if model.has_endpoint(Endpoint::Delete) {
+        code.push_str(&format!(
+            r#"
+    /// `DELETE /api/{0}/:id`
+    pub async fn delete_{0}(&self, id: {1}{2}) -> Result<(), Error> {{
+        self.send_empty(self.request(reqwest::Method::DELETE, &["api", "{0}", &id.to_string()]){3}).await
+    }}
+"#,
+            name,
+            id,
+            if model.options.versioned { ", version: i64" } else { "" },
+            if_match
+        ));
+    }
+
+    code
+}
+
+/// Everything the async and the blocking client share.
+const CLIENT_TYPES: &str = r#"
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The body of every error answer of the server.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AppError {
+    pub status_code: u16,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The server answered with an error status.
+    #[error("{} {}", .0.status_code, .0.errors.join(", "))]
+    Api(AppError),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("{0} can't be used as base url")]
+    BaseUrl(String),
+}
+
+impl Error {
+    /// The status the server answered with, if it answered.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api(e) => Some(e.status_code),
+            Error::Http(e) => e.status().map(|status| status.as_u16()),
+            Error::BaseUrl(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    /// Pass to `ListQuery::cursor` for the next page.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SearchHit<T> {
+    pub item: T,
+    pub rank: f32,
+    pub snippet: String,
+}
+
+/// A change of a row, the rows are the whole table rows by column.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HistoryEntry {
+    pub history_id: i64,
+    pub operation: String,
+    pub changed_by: String,
+    pub changed_at: chrono::DateTime<chrono::Utc>,
+    pub old_row: Option<serde_json::Value>,
+    pub new_row: Option<serde_json::Value>,
+}
+
+/// Filters, sorting and paging of a list request, fields are named like the api properties.
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery {
+    params: Vec<(String, String)>,
+}
+
+impl ListQuery {
+    pub fn new() -> Self {
+        ListQuery::default()
+    }
+
+    fn with(mut self, key: String, value: String) -> Self {
+        self.params.push((key, value));
+        self
+    }
+
+    pub fn limit(self, limit: i64) -> Self {
+        self.with("limit".to_string(), limit.to_string())
+    }
+
+    pub fn offset(self, offset: i64) -> Self {
+        self.with("offset".to_string(), offset.to_string())
+    }
+
+    pub fn cursor(self, cursor: &str) -> Self {
+        self.with("cursor".to_string(), cursor.to_string())
+    }
+
+    /// Sorts by `field`, descending with `-field`. Later calls sort rows that are equal so far.
+    pub fn sort(self, field: &str) -> Self {
+        self.with("sort".to_string(), field.to_string())
+    }
+
+    /// Embeds the related rows of `relation` into the rows.
+    pub fn include(self, relation: &str) -> Self {
+        self.with("include".to_string(), relation.to_string())
+    }
+
+    pub fn include_deleted(self) -> Self {
+        self.with("include_deleted".to_string(), "true".to_string())
+    }
+
+    pub fn eq(self, field: &str, value: impl ToString) -> Self {
+        self.with(field.to_string(), value.to_string())
+    }
+
+    pub fn lt(self, field: &str, value: impl ToString) -> Self {
+        self.with(format!("{}[lt]", field), value.to_string())
+    }
+
+    pub fn gt(self, field: &str, value: impl ToString) -> Self {
+        self.with(format!("{}[gt]", field), value.to_string())
+    }
+
+    pub fn like(self, field: &str, pattern: &str) -> Self {
+        self.with(format!("{}[like]", field), pattern.to_string())
+    }
+
+    pub fn any_of<T: ToString>(self, field: &str, values: &[T]) -> Self {
+        let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+        self.with(format!("{}[in]", field), values.join(","))
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Credentials {
+    Bearer(String),
+    ApiKey(String),
+}
+
+/// Versions are sent as strong `ETag`s like `"3"`.
+fn if_match(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+"#;
+
+/// The client itself, written async and turned into the blocking one by `to_blocking`.
+const CLIENT_CORE: &str = r#"
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: reqwest::Url,
+    http: reqwest::Client,
+    credentials: Option<Credentials>,
+}
+
+impl Client {
+    /// A client for the server at `base_url`, i.e. `http://127.0.0.1:8000`.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let base_url: reqwest::Url = base_url.parse().map_err(|_| Error::BaseUrl(base_url.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::BaseUrl(base_url.to_string()));
+        }
+        Ok(Client {
+            base_url,
+            http: reqwest::Client::new(),
+            credentials: None,
+        })
+    }
+
+    /// Sends the requests with a configured client, i.e. with timeouts or a proxy.
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_bearer_token(mut self, token: &str) -> Self {
+        self.credentials = Some(Credentials::Bearer(token.to_string()));
+        self
+    }
+
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.credentials = Some(Credentials::ApiKey(key.to_string()));
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &[&str]) -> reqwest::RequestBuilder {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().expect("checked by new").pop_if_empty().extend(path);
+        let request = self.http.request(method, url);
+        match &self.credentials {
+            Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+            Some(Credentials::ApiKey(key)) => request.header("X-Api-Key", key),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, Error> {
+        let response = Self::check(request.send().await?).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn send_empty(&self, request: reqwest::RequestBuilder) -> Result<(), Error> {
+        Self::check(request.send().await?).await?;
+        Ok(())
+    }
+
+    /// Turns an error status into `Error::Api`, with the body as error if it isn't an `AppError`.
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status_code = response.status().as_u16();
+        let body = response.text().await?;
+        let error = serde_json::from_str(&body).unwrap_or(AppError {
+            status_code,
+            errors: vec![body],
+        });
+        Err(Error::Api(error))
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use crate::json_converter::RustDataType;
+    use crate::{ModelOptions, Property};
+
+    use super::*;
+
+    fn pet() -> InternalModel {
+        let mut id = Property::new(RustDataType::I64);
+        id.read_only = true;
+        let mut name = Property::new(RustDataType::String);
+        name.required = true;
+        let mut secret = Property::new(RustDataType::String);
+        secret.write_only = true;
+        InternalModel {
+            name: "Pet".to_string(),
+            description: None,
+            properties: Some(indexmap! {"id".to_string() => id, "name".to_string() => name, "secret".to_string() => secret}),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_client_structs() {
+        let code = get_client_structs(&vec![pet()]);
+        assert!(code.contains("pub struct Pet {\npub id: Option<i64>,\npub name: String,\n}"));
+        assert!(code.contains("pub struct CreatePet {\npub name: String,\npub secret: Option<String>,\n}"));
+        assert!(code.contains("pub struct UpdatePet {\npub name: String,\npub secret: Option<String>,\n}"));
+
+        let mut model = pet();
+        model.options.skip_endpoints = vec![Endpoint::Create, Endpoint::Update];
+        let code = get_client_structs(&vec![model]);
+        assert!(!code.contains("CreatePet") && !code.contains("UpdatePet"));
+    }
+
+    #[test]
+    fn test_client_methods() {
+        let code = get_client_methods_code(&pet());
+        assert!(code.contains("pub async fn list_pet(&self, query: &ListQuery) -> Result<Page<Pet>, Error> {"));
+        assert!(code.contains("pub async fn get_pet(&self, id: i64) -> Result<Pet, Error> {"));
+        assert!(code.contains("pub async fn create_pet(&self, pet: &CreatePet) -> Result<Pet, Error> {"));
+        assert!(code.contains("pub async fn update_pet(&self, id: i64, pet: &UpdatePet) -> Result<Pet, Error> {"));
+        assert!(code.contains("pub async fn delete_pet(&self, id: i64) -> Result<(), Error> {"));
+        assert!(code.contains("self.request(reqwest::Method::DELETE, &[\"api\", \"pet\", &id.to_string()])).await"));
+        assert!(!code.contains("upsert_pet") && !code.contains("search_pet") && !code.contains("get_pet_history"));
+
+        let mut model = pet();
+        model.options.versioned = true;
+        model.options.upsert_key = Some(vec!["id".to_string()]);
+        model.options.skip_endpoints = vec![Endpoint::List];
+        let code = get_client_methods_code(&model);
+        assert!(code.contains("pub async fn delete_pet(&self, id: i64, version: i64) -> Result<(), Error> {"));
+        assert!(code.contains("pub async fn patch_pet(&self, id: i64, version: i64, patch: &serde_json::Value) -> Result<Pet, Error> {"));
+        assert!(code.contains(".header(\"If-Match\", if_match(version)).json(pet)"));
+        assert!(code.contains("pub async fn upsert_pet(&self, pet: &CreatePet) -> Result<Pet, Error> {"));
+        assert!(!code.contains("list_pet"));
+    }
+
+    #[test]
+    fn test_blocking_client() {
+        let code = create_client(&vec![pet()]);
+        let (async_client, blocking_client) = code.split_once("pub mod blocking {").unwrap();
+        assert!(async_client.contains("http: reqwest::Client,"));
+        assert!(blocking_client.contains("http: reqwest::blocking::Client,"));
+        assert!(blocking_client.contains("pub fn get_pet(&self, id: i64) -> Result<Pet, Error> {"));
+        assert!(!blocking_client.contains(".await") && !blocking_client.contains("async fn"));
+        assert!(!blocking_client.contains("pub struct Pet"));
+    }
+}
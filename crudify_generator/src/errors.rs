@@ -1,19 +1,19 @@
 use serde_json::Value;
 use thiserror::Error;
 
-use crate::json_converter::RustDataType;
-
 // from https://www.reddit.com/r/rust/comments/gj8inf/comment/fqlmknt/
 #[derive(Error, Debug)]
 pub enum JsonConverterError<'a> {
     #[error("Could not convert value to object: {0}")]
     AsObjectError(&'a Value),
-}
-
-#[derive(Error, Debug)]
-pub enum SqlConverterError<'a> {
-    #[error("DataType not found: {0}")]
-    DataTypeNotFound(&'a RustDataType),
+    #[error("Index on {0} references unknown property: {1}")]
+    UnknownIndexColumn(String, String),
+    #[error("Upsert key of {0} must consist of writable properties with a unique index: {1}")]
+    InvalidUpsertKey(String, String),
+    #[error("Only string properties can be searchable: {0}.{1}")]
+    UnsearchableProperty(String, String),
+    #[error("{0}.{1} can't be a rust field, give it another column name with x-crudify-rename")]
+    UnnamableProperty(String, String),
 }
 
 // impl Error for JsonConverterError<'_> {
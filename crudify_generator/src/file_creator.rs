@@ -2,7 +2,15 @@ use std::{fs, io::Write, path::PathBuf};
 
 use super::InternalModels;
 
+use super::client_creator::create_client;
+use super::graphql_creator::has_graphql;
+use super::grpc_creator::{create_grpc_service, create_proto, has_grpc};
 use super::main_file_creator::write_main_file;
+use super::openapi_creator::create_openapi;
+use super::sql_creator::create_migration;
+use super::test_creator::create_api_tests;
+use super::ts_client_creator::{create_ts_client, create_ts_models};
+use super::web_backend::{Axum, WebBackend};
 
 pub fn create_or_get_project_dir(user_id: &str) -> Result<PathBuf, std::io::Error> {
     let current_dir = std::env::current_dir()?;
@@ -11,18 +19,110 @@ pub fn create_or_get_project_dir(user_id: &str) -> Result<PathBuf, std::io::Erro
     Ok(data_path)
 }
 
-fn write_cargo_toml(user_id: &str) -> Result<(), std::io::Error> {
+/// The template depends on axum, other web backends replace it with their framework. The GraphQL schema needs
+/// `async-graphql`, it is only added for models that opted in. The gRPC services need `tonic` and the protobuf compiler
+/// of `build.rs`.
+fn write_cargo_toml(user_id: &str, models: &InternalModels, backend: &dyn WebBackend) -> Result<(), std::io::Error> {
     let cargo_toml = include_str!("../templates/Cargo.toml");
-    let cargo_toml = cargo_toml.replace("name = \"\"", format!("name = \"{}\"", user_id).as_str());
+    let mut cargo_toml = cargo_toml
+        .replace("name = \"\"", format!("name = \"{}\"", user_id).as_str())
+        .replace(Axum.dependencies(), backend.dependencies());
+    if has_graphql(models) {
+        cargo_toml = cargo_toml.replace(
+            "\n[dev-dependencies]",
+            "async-graphql = { version = \"7\", default-features = false, features = [\"chrono\", \"dataloader\"] }\n\n[dev-dependencies]",
+        );
+    }
+    if has_grpc(models) {
+        cargo_toml = cargo_toml.replace("\n[dev-dependencies]", "tonic = \"0.8\"\nprost = \"0.11\"\n\n[dev-dependencies]");
+        cargo_toml = cargo_toml.replace(
+            "\n[workspace]",
+            "[build-dependencies]\ntonic-build = \"0.8\"\nprotoc-bin-vendored = \"3\"\n\n[workspace]",
+        );
+    }
     let data_path = create_or_get_project_dir(user_id)?.join("Cargo.toml");
     let mut main_rs = fs::File::create(data_path)?;
     main_rs.write_all(cargo_toml.as_bytes())?;
     Ok(())
 }
 
-pub fn write_all(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
-    write_cargo_toml(user_id)?;
-    write_main_file(user_id, models)?;
+/// Dumps the intermediate representation next to the generated project, so it can be inspected and diffed between runs.
+fn write_internal_model(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let data_path = create_or_get_project_dir(user_id)?.join("internal_model.json");
+    let mut internal_model_json = fs::File::create(data_path)?;
+    internal_model_json.write_all(serde_json::to_string_pretty(models)?.as_bytes())?;
+    Ok(())
+}
+
+/// The schema as a sqlx migration, for databases that are not set up by the generated server itself.
+fn write_migrations(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let migrations_path = create_or_get_project_dir(user_id)?.join("migrations");
+    fs::create_dir_all(&migrations_path)?;
+    let mut migration = fs::File::create(migrations_path.join("0001_create_tables.sql"))?;
+    migration.write_all(create_migration(models).as_bytes())?;
+    Ok(())
+}
+
+/// The OpenAPI document of the generated api, the server embeds it to serve it at `/api/openapi.json`.
+fn write_openapi(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let data_path = create_or_get_project_dir(user_id)?.join("openapi.json");
+    let mut openapi_json = fs::File::create(data_path)?;
+    openapi_json.write_all(serde_json::to_string_pretty(&create_openapi(user_id, models))?.as_bytes())?;
+    Ok(())
+}
+
+/// The `{user_id}_client` crate in `client`, a member of the workspace of the generated project.
+fn write_client(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let client_path = create_or_get_project_dir(user_id)?.join("client");
+    fs::create_dir_all(client_path.join("src"))?;
+    let cargo_toml = include_str!("../templates/client/Cargo.toml");
+    let cargo_toml = cargo_toml.replace("name = \"\"", format!("name = \"{}_client\"", user_id).as_str());
+    fs::File::create(client_path.join("Cargo.toml"))?.write_all(cargo_toml.as_bytes())?;
+    fs::File::create(client_path.join("src").join("lib.rs"))?.write_all(create_client(models).as_bytes())?;
+    Ok(())
+}
+
+/// The TypeScript client in `clients/ts`, the models in `models.ts` and the client in `client.ts`.
+fn write_ts_client(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let ts_path = create_or_get_project_dir(user_id)?.join("clients").join("ts");
+    fs::create_dir_all(&ts_path)?;
+    fs::File::create(ts_path.join("models.ts"))?.write_all(create_ts_models(models).as_bytes())?;
+    fs::File::create(ts_path.join("client.ts"))?.write_all(create_ts_client(models).as_bytes())?;
+    Ok(())
+}
+
+/// The protos in `proto/crudify.proto`, the `build.rs` compiling them and the tonic services in `src/grpc.rs`.
+fn write_grpc(user_id: &str, models: &InternalModels, backend: &dyn WebBackend) -> Result<(), std::io::Error> {
+    if !has_grpc(models) {
+        return Ok(());
+    }
+    let project_path = create_or_get_project_dir(user_id)?;
+    fs::create_dir_all(project_path.join("proto"))?;
+    fs::create_dir_all(project_path.join("src"))?;
+    fs::File::create(project_path.join("proto").join("crudify.proto"))?.write_all(create_proto(models).as_bytes())?;
+    fs::File::create(project_path.join("build.rs"))?.write_all(include_str!("../templates/build.rs").as_bytes())?;
+    fs::File::create(project_path.join("src").join("grpc.rs"))?.write_all(create_grpc_service(models, backend).as_bytes())?;
+    Ok(())
+}
+
+/// The api tests of every model in `tests/api.rs`.
+fn write_api_tests(user_id: &str, models: &InternalModels) -> Result<(), std::io::Error> {
+    let tests_path = create_or_get_project_dir(user_id)?.join("tests");
+    fs::create_dir_all(&tests_path)?;
+    fs::File::create(tests_path.join("api.rs"))?.write_all(create_api_tests(models).as_bytes())?;
+    Ok(())
+}
+
+pub fn write_all(user_id: &str, models: &InternalModels, backend: &dyn WebBackend) -> Result<(), std::io::Error> {
+    write_cargo_toml(user_id, models, backend)?;
+    write_internal_model(user_id, models)?;
+    write_migrations(user_id, models)?;
+    write_openapi(user_id, models)?;
+    write_client(user_id, models)?;
+    write_ts_client(user_id, models)?;
+    write_grpc(user_id, models, backend)?;
+    write_main_file(user_id, models, backend)?;
+    write_api_tests(user_id, models)?;
     Ok(())
 }
 
@@ -0,0 +1,736 @@
+use crate::main_file_creator::{
+    get_auth_code, get_id_type, get_linked_relations, get_readable_id, get_rust_type, has_auth, is_omittable, HandlerAuth, LinkedRelation,
+};
+use crate::sql_creator::{create_get_related, create_sqlite_get_related};
+use crate::web_backend::WebBackend;
+use crate::{Endpoint, InternalModel, InternalModels, RelationKind};
+
+/// GraphQL is served if a model that opted in with `x-crudify-graphql` can be listed, a schema needs a query field.
+pub(crate) fn has_graphql(models: &InternalModels) -> bool {
+    models.iter().any(|model| model.options.graphql && model.has_endpoint(Endpoint::List))
+}
+
+/// The model is a type of the GraphQL schema.
+pub(crate) fn is_graphql(model: &InternalModel, models: &InternalModels) -> bool {
+    model.options.graphql && has_graphql(models)
+}
+
+/// A relation resolved on a GraphQL type, both models have to be in the schema.
+struct GraphqlRelation<'a> {
+    linked: LinkedRelation<'a>,
+    /// Expression for the `Option<String>` key of `self` the related rows are looked up by.
+    key: String,
+    /// The property of the related rows that matches the key.
+    related_key: &'a str,
+}
+
+impl GraphqlRelation<'_> {
+    /// The key the loader batches the relation under, i.e. `pet.category`.
+    fn loader_name(&self, model: &InternalModel) -> String {
+        format!("{}.{}", model.name.to_lowercase(), self.linked.relation.name)
+    }
+}
+
+fn get_graphql_relations<'a>(model: &'a InternalModel, models: &'a InternalModels) -> Vec<GraphqlRelation<'a>> {
+    if !is_graphql(model, models) {
+        return vec![];
+    }
+    get_linked_relations(model, models)
+        .into_iter()
+        .filter(|linked| linked.has_get(model) && is_graphql(linked.target, models))
+        .filter_map(|linked| {
+            // the key is read from `self`, the related key from the json of the related rows
+            let (key, related_key) = match linked.relation.kind {
+                RelationKind::BelongsTo => (linked.foreign_key, "id"),
+                RelationKind::HasMany => ("id", linked.foreign_key),
+            };
+            let property = model.properties.as_ref()?.get(key).filter(|property| property.is_readable() && !property.is_array)?;
+            linked.target.properties.as_ref()?.get(related_key).filter(|property| property.is_readable())?;
+            let field = property.field_name(key);
+            let key = if get_rust_type(property).starts_with("Option<") {
+                format!("self.{}.as_ref().map(|key| key.to_string())", field)
+            } else {
+                format!("Some(self.{}.to_string())", field)
+            };
+            Some(GraphqlRelation { linked, key, related_key })
+        })
+        .collect()
+}
+
+pub(crate) fn has_graphql_relations(model: &InternalModel, models: &InternalModels) -> bool {
+    !get_graphql_relations(model, models).is_empty()
+}
+
+/// The `async-graphql` schema of the models that opted in: `QueryRoot`, `MutationRoot`, the relation loader and the
+/// `POST /graphql` handler. Resolvers call the same queries and checks as the REST handlers.
+pub(crate) fn create_graphql(models: &InternalModels, backend: &dyn WebBackend) -> String {
+    if !has_graphql(models) {
+        return "".to_string();
+    }
+    let graphql_models: Vec<&InternalModel> = models.iter().filter(|model| is_graphql(model, models)).collect();
+    let mut code = GRAPHQL_SETUP.to_string();
+    if has_auth(models) {
+        code.push_str(REQUIRE_PRINCIPAL_FN);
+    }
+    let mut queries = "".to_string();
+    let mut mutations = "".to_string();
+    for model in &graphql_models {
+        code.push_str(&get_resolver_fns_code(model));
+        queries.push_str(&get_query_fields_code(model));
+        mutations.push_str(&get_mutation_fields_code(model));
+        code.push_str(&get_complex_object_code(model, models));
+    }
+    code.push_str(&format!(
+        "\nstruct QueryRoot;\n\n#[allow(clippy::too_many_arguments)]\n#[async_graphql::Object]\nimpl QueryRoot {{\n{}}}\n",
+        queries
+    ));
+    let mutation_root = if mutations.is_empty() {
+        "async_graphql::EmptyMutation"
+    } else {
+        code.push_str(&format!("\nstruct MutationRoot;\n\n#[async_graphql::Object]\nimpl MutationRoot {{\n{}}}\n", mutations));
+        "MutationRoot"
+    };
+    code.push_str(&format!(
+        r#"
+type GraphqlSchema = async_graphql::Schema<QueryRoot, {0}, async_graphql::EmptySubscription>;
+
+fn graphql_schema() -> GraphqlSchema {{
+    async_graphql::Schema::build(QueryRoot, {0}, async_graphql::EmptySubscription).finish()
+}}
+"#,
+        mutation_root
+    ));
+    let has_relations = graphql_models.iter().any(|model| has_graphql_relations(model, models));
+    if has_relations {
+        code.push_str(&get_relation_loader_code(models));
+    }
+    code.push_str(&get_graphql_handler_code(models, has_relations, backend));
+
+    code
+}
+
+/// The principal parameter and the code that makes sure there is one, for a resolver that runs the `auth` checks.
+/// Resolvers of open models don't take a principal.
+fn get_principal_code(auth: &HandlerAuth) -> (&'static str, &'static str, &'static str) {
+    if auth.param.is_empty() {
+        ("", "", "")
+    } else if auth.param.starts_with('_') {
+        ("principal: Option<&Principal>, ", "require_principal(principal)?;\n", "ctx.data_opt::<Principal>(), ")
+    } else {
+        ("principal: Option<&Principal>, ", "let principal = require_principal(principal)?;\n", "ctx.data_opt::<Principal>(), ")
+    }
+}
+
+/// The resolvers work on the pool and the principal of the request, like the handlers on their extractors.
+fn get_resolver_fns_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let id_type = get_id_type(model);
+    let (version_param, version_arg) = if model.options.versioned {
+        ("version: i64, ", "Some(version), ")
+    } else {
+        ("", "")
+    };
+    let mut code = "".to_string();
+    if model.has_endpoint(Endpoint::List) {
+        let auth = get_auth_code(&[(model, Endpoint::List)], None);
+        let (param, principal, _) = get_principal_code(&auth);
+        code.push_str(&format!(
+            r#"
+            #[derive(Deserialize, async_graphql::SimpleObject)]
+            struct {1}Page {{
+                items: Vec<{1}>,
+                total: i64,
+                next_cursor: Option<String>,
+            }}
+
+            async fn graphql_{0}_list(pool: DbPool, {2}params: Vec<(String, String)>) -> Result<{1}Page, AppError> {{
+                {3}{4}{5}from_json(list_{0}(&mut conn, &params).await?)
+            }}
+            "#,
+            name,
+            model.name,
+            param,
+            principal,
+            auth.checks,
+            auth.connect()
+        ));
+        if get_readable_id(model).is_some() {
+            code.push_str(&format!(
+                r#"
+            async fn graphql_{0}(pool: DbPool, {2}id: {3}) -> Result<Option<{1}>, AppError> {{
+                let params = vec![("id".to_string(), id.to_string())];
+                Ok(graphql_{0}_list(pool, {4}params).await?.items.pop())
+            }}
+            "#,
+                name,
+                model.name,
+                param,
+                id_type,
+                if param.is_empty() { "" } else { "principal, " }
+            ));
+        }
+    }
+    if model.has_endpoint(Endpoint::Create) {
+        let auth = get_auth_code(&[(model, Endpoint::Create)], None);
+        let (param, principal, _) = get_principal_code(&auth);
+        code.push_str(&format!(
+            r#"
+            async fn graphql_create_{0}(pool: DbPool, {2}input: Create{1}) -> Result<{1}, AppError> {{
+                {3}{4}{5}let res = insert_{0}(&mut conn, &input).await?;
+                {6}Ok(res)
+            }}
+            "#,
+            name,
+            model.name,
+            param,
+            principal,
+            auth.checks,
+            auth.connect(),
+            auth.commit()
+        ));
+    }
+    if model.has_endpoint(Endpoint::Update) {
+        let auth = get_auth_code(&[(model, Endpoint::Update)], None);
+        let (param, principal, _) = get_principal_code(&auth);
+        // `null` can't be stored in a column the database defaults, such a value is kept instead
+        let defaulted: Vec<String> = model
+            .properties
+            .iter()
+            .flatten()
+            .filter(|(key, property)| *key != "id" && property.is_writable() && is_omittable(property) && !property.is_optional())
+            .map(|(key, _)| format!("\"{}\"", key))
+            .collect();
+        let (patch, retain) = if defaulted.is_empty() {
+            ("patch", "".to_string())
+        } else {
+            (
+                "mut patch",
+                format!(
+                    "patch.retain(|key, value| !value.is_null() || ![{}].contains(&key.as_str()));\n",
+                    defaulted.join(", ")
+                ),
+            )
+        };
+        code.push_str(&format!(
+            r#"
+            async fn graphql_update_{0}(pool: DbPool, {2}id: {3}, {4}input: Update{1}) -> Result<{1}, AppError> {{
+                let Value::Object({5}) = json!(input) else {{
+                    return Err(AppError::new_internal("An input is serialized to an object".to_string()));
+                }};
+                {6}{7}{8}{9}let res = patch_{0}_row(&mut conn, id, {10}patch).await?;
+                {11}Ok(res)
+            }}
+            "#,
+            name,
+            model.name,
+            param,
+            id_type,
+            version_param,
+            patch,
+            retain,
+            principal,
+            auth.checks,
+            auth.connect(),
+            version_arg,
+            auth.commit()
+        ));
+    }
+    if model.has_endpoint(Endpoint::Delete) {
+        let auth = get_auth_code(&[(model, Endpoint::Delete)], None);
+        let (param, principal, _) = get_principal_code(&auth);
+        code.push_str(&format!(
+            r#"
+            async fn graphql_delete_{0}(pool: DbPool, {1}id: {2}, {3}) -> Result<bool, AppError> {{
+                {4}{5}{6}delete_{0}_row(&mut conn, {7}id).await?;
+                {8}Ok(true)
+            }}
+            "#,
+            name,
+            param,
+            id_type,
+            version_param.trim_end_matches(", "),
+            principal,
+            auth.checks,
+            auth.connect(),
+            version_arg,
+            auth.commit()
+        ));
+    }
+
+    code
+}
+
+/// `pet(id)` and `petList(limit, offset, cursor, sort, filters)` like `GET /api/pet/:id` and `GET /api/pet`.
+fn get_query_fields_code(model: &InternalModel) -> String {
+    if !model.has_endpoint(Endpoint::List) {
+        return "".to_string();
+    }
+    let name = model.name.to_lowercase();
+    let auth = get_auth_code(&[(model, Endpoint::List)], None);
+    let (_, _, principal) = get_principal_code(&auth);
+    let (include_deleted_param, include_deleted) = if model.options.soft_delete {
+        ("include_deleted: Option<bool>, ", "include_deleted")
+    } else {
+        ("", "None")
+    };
+    let mut code = "".to_string();
+    if get_readable_id(model).is_some() {
+        code.push_str(&format!(
+            r#"
+    /// The {0} with the `id`, `null` if there is none.
+    async fn {0}(&self, ctx: &async_graphql::Context<'_>, id: {2}) -> async_graphql::Result<Option<{1}>> {{
+        graphql_{0}(ctx.data_unchecked::<DbPool>().clone(), {3}id).await.map_err(graphql_error)
+    }}
+"#,
+            name,
+            model.name,
+            get_id_type(model),
+            principal
+        ));
+    }
+    code.push_str(&format!(
+        r#"
+    /// A page of the {0} rows matching the `filters`, `sort` takes property names with a `-` prefix to sort descending.
+    async fn {0}_list(
+        &self,
+        ctx: &async_graphql::Context<'_>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        cursor: Option<String>,
+        sort: Option<Vec<String>>,
+        filters: Option<Vec<Filter>>,
+        {2}
+    ) -> async_graphql::Result<{1}Page> {{
+        let params = list_params(limit, offset, cursor, sort, filters, {3});
+        graphql_{0}_list(ctx.data_unchecked::<DbPool>().clone(), {4}params).await.map_err(graphql_error)
+    }}
+"#,
+        name,
+        model.name,
+        include_deleted_param,
+        include_deleted,
+        principal
+    ));
+
+    code
+}
+
+/// `createPet(input)`, `updatePet(id, input)` and `deletePet(id)`, the latter two take the `version` of a versioned model.
+fn get_mutation_fields_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let id_type = get_id_type(model);
+    let (version_param, version_arg) = if model.options.versioned {
+        ("version: i64, ", "version, ")
+    } else {
+        ("", "")
+    };
+    let mut code = "".to_string();
+    if model.has_endpoint(Endpoint::Create) {
+        let (_, _, principal) = get_principal_code(&get_auth_code(&[(model, Endpoint::Create)], None));
+        code.push_str(&format!(
+            r#"
+    async fn create_{0}(&self, ctx: &async_graphql::Context<'_>, input: Create{1}) -> async_graphql::Result<{1}> {{
+        graphql_create_{0}(ctx.data_unchecked::<DbPool>().clone(), {2}input).await.map_err(graphql_error)
+    }}
+"#,
+            name, model.name, principal
+        ));
+    }
+    if model.has_endpoint(Endpoint::Update) {
+        let (_, _, principal) = get_principal_code(&get_auth_code(&[(model, Endpoint::Update)], None));
+        code.push_str(&format!(
+            r#"
+    /// Replaces all writable properties of the {0}.
+    async fn update_{0}(&self, ctx: &async_graphql::Context<'_>, id: {2}, {3}input: Update{1}) -> async_graphql::Result<{1}> {{
+        graphql_update_{0}(ctx.data_unchecked::<DbPool>().clone(), {4}id, {5}input).await.map_err(graphql_error)
+    }}
+"#,
+            name, model.name, id_type, version_param, principal, version_arg
+        ));
+    }
+    if model.has_endpoint(Endpoint::Delete) {
+        let (_, _, principal) = get_principal_code(&get_auth_code(&[(model, Endpoint::Delete)], None));
+        code.push_str(&format!(
+            r#"
+    async fn delete_{0}(&self, ctx: &async_graphql::Context<'_>, id: {1}, {2}) -> async_graphql::Result<bool> {{
+        graphql_delete_{0}(ctx.data_unchecked::<DbPool>().clone(), {3}id, {4}).await.map_err(graphql_error)
+    }}
+"#,
+            name,
+            id_type,
+            version_param.trim_end_matches(", "),
+            principal,
+            version_arg.trim_end_matches(", ")
+        ));
+    }
+
+    code
+}
+
+/// The relations of a type, resolved by the loader of the request so all rows of a list share one query per relation.
+fn get_complex_object_code(model: &InternalModel, models: &InternalModels) -> String {
+    let relations = get_graphql_relations(model, models);
+    if relations.is_empty() {
+        return "".to_string();
+    }
+    let fields: String = relations
+        .iter()
+        .map(|relation| {
+            let field = relation.linked.route_name();
+            let rename = if field != relation.linked.relation.name {
+                format!("#[graphql(name = \"{}\")]\n", relation.linked.relation.name)
+            } else {
+                "".to_string()
+            };
+            let load = format!("load_related(ctx, \"{}\", {}).await", relation.loader_name(model), relation.key);
+            let (result, related) = match relation.linked.relation.kind {
+                RelationKind::BelongsTo => (
+                    format!("Option<{}>", relation.linked.target.name),
+                    format!("Ok({}?.into_iter().next())", load),
+                ),
+                RelationKind::HasMany => (format!("Vec<{}>", relation.linked.target.name), load),
+            };
+            format!(
+                r#"
+    {0}async fn {1}(&self, ctx: &async_graphql::Context<'_>) -> async_graphql::Result<{2}> {{
+        {3}
+    }}
+"#,
+                rename, field, result, related
+            )
+        })
+        .collect();
+
+    format!("\n#[async_graphql::ComplexObject]\nimpl {} {{\n{}}}\n", model.name, fields)
+}
+
+/// The relations of all types with the auth of listing their targets.
+fn get_loaded_relations(models: &InternalModels) -> Vec<(&InternalModel, GraphqlRelation<'_>, HandlerAuth)> {
+    models
+        .iter()
+        .flat_map(|model| get_graphql_relations(model, models).into_iter().map(move |relation| (model, relation)))
+        .map(|(model, relation)| {
+            let auth = get_auth_code(&[(relation.linked.target, Endpoint::List)], None);
+            (model, relation, auth)
+        })
+        .collect()
+}
+
+/// The loader only holds a principal if a related model is protected.
+fn loader_needs_principal(models: &InternalModels) -> bool {
+    get_loaded_relations(models).iter().any(|(_, _, auth)| !auth.param.is_empty())
+}
+
+/// `load_relation` runs the checks and the query of one relation for the keys of all rows a request resolves it on.
+fn get_relation_loader_code(models: &InternalModels) -> String {
+    let mut arms = "".to_string();
+    for (model, relation, auth) in get_loaded_relations(models) {
+        let target = relation.linked.target;
+        let (_, principal, _) = get_principal_code(&auth);
+        arms.push_str(&format!(
+            r#""{0}" => {{
+                {1}{2}{3}let query = if SQLITE {{ {7:?} }} else {{ {5:?} }};
+                let related = sqlx::query_as::<_, {4}>(query).bind(db_array(keys)).fetch_all(&mut *conn).await?;
+                Ok(("{6}", related.iter().map(|row| json!(row)).collect()))
+            }}
+            "#,
+            relation.loader_name(model),
+            principal,
+            auth.checks,
+            auth.connect(),
+            target.name,
+            create_get_related(target, relation.related_key),
+            relation.related_key,
+            create_sqlite_get_related(target, relation.related_key)
+        ));
+    }
+    let (principal_field, principal_param, principal_arg) = if loader_needs_principal(models) {
+        ("    principal: Option<Principal>,\n", "principal: Option<&Principal>, ", "self.principal.as_ref(), ")
+    } else {
+        ("", "", "")
+    };
+
+    format!(
+        r#"
+/// Loads the related rows of one relation, the json of each row paired with the property its key is matched on.
+async fn load_relation(pool: DbPool, {1}relation: &str, keys: Vec<String>) -> Result<(&'static str, Vec<Value>), AppError> {{
+    match relation {{
+        {0}_ => Err(AppError::new_internal(format!("Unknown relation {{}}", relation))),
+    }}
+}}
+
+/// Batches the relations the resolvers of one request load.
+struct RelationLoader {{
+    pool: DbPool,
+{2}}}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct RelationKey {{
+    relation: &'static str,
+    key: String,
+}}
+
+impl async_graphql::dataloader::Loader<RelationKey> for RelationLoader {{
+    type Value = Vec<Value>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[RelationKey]) -> Result<std::collections::HashMap<RelationKey, Vec<Value>>, async_graphql::Error> {{
+        let mut relations: Vec<&'static str> = keys.iter().map(|key| key.relation).collect();
+        relations.sort();
+        relations.dedup();
+        let mut loaded = std::collections::HashMap::new();
+        for relation in relations {{
+            let relation_keys: Vec<String> = keys.iter().filter(|key| key.relation == relation).map(|key| key.key.clone()).collect();
+            let (related_key, related) = load_relation(self.pool.clone(), {3}relation, relation_keys.clone()).await.map_err(graphql_error)?;
+            for key in relation_keys {{
+                let rows = related.iter().filter(|row| include_key(&row[related_key]).as_deref() == Some(key.as_str())).cloned().collect();
+                loaded.insert(RelationKey {{ relation, key }}, rows);
+            }}
+        }}
+        Ok(loaded)
+    }}
+}}
+
+async fn load_related<T: serde::de::DeserializeOwned>(
+    ctx: &async_graphql::Context<'_>,
+    relation: &'static str,
+    key: Option<String>,
+) -> async_graphql::Result<Vec<T>> {{
+    let Some(key) = key else {{
+        return Ok(vec![]);
+    }};
+    let loader = ctx.data_unchecked::<async_graphql::dataloader::DataLoader<RelationLoader>>();
+    let rows = loader.load_one(RelationKey {{ relation, key }}).await?.unwrap_or_default();
+    rows.into_iter().map(|row| from_json(row).map_err(graphql_error)).collect()
+}}
+"#,
+        arms, principal_param, principal_field, principal_arg
+    )
+}
+
+/// `POST /graphql` runs a request with the pool, the principal if there are credentials, and a fresh relation loader.
+/// Resolvers that need a principal answer with an `UNAUTHORIZED` error without one.
+fn get_graphql_handler_code(models: &InternalModels, has_relations: bool, backend: &dyn WebBackend) -> String {
+    let auth = has_auth(models);
+    let loader = if !has_relations {
+        ""
+    } else if loader_needs_principal(models) {
+        "let loader = async_graphql::dataloader::DataLoader::new(RelationLoader { pool: pool.clone(), principal: principal.clone() }, tokio::spawn);\nrequest = request.data(loader);\n"
+    } else {
+        "let loader = async_graphql::dataloader::DataLoader::new(RelationLoader { pool: pool.clone() }, tokio::spawn);\nrequest = request.data(loader);\n"
+    };
+    let (param, principal) = if auth {
+        ("principal: Option<Principal>, ", "if let Some(principal) = principal {\nrequest = request.data(principal);\n}\n")
+    } else {
+        ("", "")
+    };
+
+    format!(
+        r#"
+async fn graphql({0}{3}, {4}, Json(request): Json<async_graphql::Request>) -> Json<async_graphql::Response> {{
+    {5}let mut request = request;
+    {1}{2}request = request.data(pool);
+    Json(schema.execute(request).await)
+}}
+"#,
+        param,
+        loader,
+        principal,
+        backend.state_param("schema", "GraphqlSchema"),
+        backend.pool_param(),
+        backend.owned_state("pool", "DbPool")
+    )
+}
+
+const GRAPHQL_SETUP: &str = r#"
+/// The errors of an `AppError` as one GraphQL error, with its http status as `status` extension.
+fn graphql_error(error: AppError) -> async_graphql::Error {
+    let status = error.status_code;
+    async_graphql::ErrorExtensions::extend_with(async_graphql::Error::new(error.errors.join(", ")), |_, extensions| {
+        extensions.set("status", status)
+    })
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, AppError> {
+    serde_json::from_value(value).map_err(|e| AppError::new_internal(e.to_string()))
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Default, Eq, PartialEq)]
+enum FilterOp {
+    #[default]
+    Eq,
+    Lt,
+    Gt,
+    Like,
+    /// The value is a comma separated list.
+    In,
+}
+
+/// `field[op]=value` of a REST list request.
+#[derive(async_graphql::InputObject)]
+struct Filter {
+    /// The name of a property in the api.
+    field: String,
+    #[graphql(default)]
+    op: FilterOp,
+    value: String,
+}
+
+/// The query parameters of the REST list request with the same arguments.
+fn list_params(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    cursor: Option<String>,
+    sort: Option<Vec<String>>,
+    filters: Option<Vec<Filter>>,
+    include_deleted: Option<bool>,
+) -> Vec<(String, String)> {
+    let mut params = vec![];
+    if let Some(limit) = limit {
+        params.push(("limit".to_string(), limit.to_string()));
+    }
+    if let Some(offset) = offset {
+        params.push(("offset".to_string(), offset.to_string()));
+    }
+    if let Some(cursor) = cursor {
+        params.push(("cursor".to_string(), cursor));
+    }
+    if let Some(sort) = sort {
+        params.push(("sort".to_string(), sort.join(",")));
+    }
+    if include_deleted == Some(true) {
+        params.push(("include_deleted".to_string(), "true".to_string()));
+    }
+    for filter in filters.unwrap_or_default() {
+        let op = match filter.op {
+            FilterOp::Eq => "eq",
+            FilterOp::Lt => "lt",
+            FilterOp::Gt => "gt",
+            FilterOp::Like => "like",
+            FilterOp::In => "in",
+        };
+        params.push((format!("{}[{}]", filter.field, op), filter.value));
+    }
+    params
+}
+"#;
+
+const REQUIRE_PRINCIPAL_FN: &str = r#"
+fn require_principal(principal: Option<&Principal>) -> Result<Principal, AppError> {
+    principal.cloned().ok_or_else(|| AppError::new_unauthorized("Credentials are required".to_string()))
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use crate::json_converter::RustDataType;
+    use crate::{Access, ModelOptions, Property, Relation};
+    use crate::web_backend::Axum;
+
+    use super::*;
+
+    fn models() -> InternalModels {
+        let mut id = Property::new(RustDataType::I64);
+        id.read_only = true;
+        let mut name = Property::new(RustDataType::String);
+        name.required = true;
+        let category_id = Property::new(RustDataType::I64);
+        let pet = InternalModel {
+            name: "Pet".to_string(),
+            description: None,
+            properties: Some(indexmap! {
+                "id".to_string() => id.clone(),
+                "name".to_string() => name.clone(),
+                "category_id".to_string() => category_id,
+            }),
+            relations: vec![Relation {
+                name: "category".to_string(),
+                target: "Category".to_string(),
+                kind: RelationKind::BelongsTo,
+                required: false,
+                description: None,
+                foreign_key: Some("category_id".to_string()),
+            }],
+            indexes: vec![],
+            options: ModelOptions {
+                graphql: true,
+                soft_delete: true,
+                ..ModelOptions::default()
+            },
+        };
+        let category = InternalModel {
+            name: "Category".to_string(),
+            description: None,
+            properties: Some(indexmap! {"id".to_string() => id, "name".to_string() => name}),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions {
+                graphql: true,
+                versioned: true,
+                access: Some(Access {
+                    list: vec!["reader".to_string()],
+                    ..Access::default()
+                }),
+                ..ModelOptions::default()
+            },
+        };
+        vec![pet, category]
+    }
+
+    #[test]
+    fn test_opt_in() {
+        let mut models = models();
+        assert!(create_graphql(&models, &Axum).contains("fn graphql_schema() -> GraphqlSchema"));
+        assert!(has_graphql_relations(&models[0], &models));
+
+        // a relation needs both models in the schema
+        models[1].options.graphql = false;
+        assert!(is_graphql(&models[0], &models));
+        assert!(!has_graphql_relations(&models[0], &models));
+
+        models[0].options.skip_endpoints = vec![Endpoint::List];
+        assert!(!has_graphql(&models));
+        assert_eq!("", create_graphql(&models, &Axum));
+    }
+
+    #[test]
+    fn test_query_and_mutation_fields() {
+        let models = models();
+        let code = create_graphql(&models, &Axum);
+        assert!(code.contains("async fn pet(&self, ctx: &async_graphql::Context<'_>, id: i64) -> async_graphql::Result<Option<Pet>>"));
+        assert!(code.contains("include_deleted: Option<bool>, \n    ) -> async_graphql::Result<PetPage>"));
+        assert!(code.contains("let params = list_params(limit, offset, cursor, sort, filters, include_deleted);"));
+        assert!(code.contains("let params = list_params(limit, offset, cursor, sort, filters, None);"));
+        assert!(code.contains("from_json(list_pet(&mut conn, &params).await?)"));
+        assert!(code.contains("let res = insert_pet(&mut conn, &input).await?;"));
+        assert!(code.contains("type GraphqlSchema = async_graphql::Schema<QueryRoot, MutationRoot, async_graphql::EmptySubscription>;"));
+
+        // versioned rows are changed at a version, protected ones by a principal with the role
+        assert!(code.contains("async fn update_category(&self, ctx: &async_graphql::Context<'_>, id: i64, version: i64, input: UpdateCategory)"));
+        assert!(code.contains("let res = patch_category_row(&mut conn, id, Some(version), patch).await?;"));
+        assert!(code.contains("delete_category_row(&mut conn, Some(version), id).await?;"));
+        assert!(code.contains(
+            "async fn graphql_category_list(pool: DbPool, principal: Option<&Principal>, params: Vec<(String, String)>)"
+        ));
+        assert!(code.contains("let principal = require_principal(principal)?;\nprincipal.require(&[\"reader\"])?;"));
+        assert!(code.contains("graphql_category(ctx.data_unchecked::<DbPool>().clone(), ctx.data_opt::<Principal>(), id)"));
+    }
+
+    #[test]
+    fn test_relations() {
+        let models = models();
+        let code = create_graphql(&models, &Axum);
+        assert!(code.contains("#[async_graphql::ComplexObject]\nimpl Pet {"));
+        assert!(code.contains(
+            "Ok(load_related(ctx, \"pet.category\", self.category_id.as_ref().map(|key| key.to_string())).await?.into_iter().next())"
+        ));
+        assert!(code.contains("\"pet.category\" => {\n                let principal = require_principal(principal)?;"));
+        assert!(code.contains("let query = if SQLITE { \"SELECT"));
+        assert!(code.contains("sqlx::query_as::<_, Category>(query).bind(db_array(keys))"));
+        assert!(code.contains("Ok((\"id\", related.iter().map(|row| json!(row)).collect()))"));
+        assert!(code.contains("RelationLoader { pool: pool.clone(), principal: principal.clone() }"));
+        assert!(code.contains("if let Some(principal) = principal {\nrequest = request.data(principal);\n}"));
+    }
+}
@@ -0,0 +1,770 @@
+use crate::internal_model::rust_identifier;
+use crate::json_converter::RustDataType;
+use crate::main_file_creator::{
+    get_auth_code, get_default_fn_name, get_fetch_changed_code, get_readable_id, get_rust_default_value, get_rust_type, get_struct_properties,
+    get_update_binds_code,
+};
+use crate::sql_creator::create_update_entity;
+use crate::web_backend::WebBackend;
+use crate::{Endpoint, InternalModel, InternalModels, Property};
+
+/// The protobuf package of the services, `tonic::include_proto!` needs it to find the generated code.
+pub const PROTO_PACKAGE: &str = "crudify";
+
+const RPC_ENDPOINTS: [Endpoint; 4] = [Endpoint::List, Endpoint::Create, Endpoint::Update, Endpoint::Delete];
+
+/// Only models that opted in with `x-crudify-grpc` get a service, the others are REST only.
+fn has_service(model: &InternalModel) -> bool {
+    model.options.grpc && RPC_ENDPOINTS.iter().any(|endpoint| model.has_endpoint(*endpoint))
+}
+
+pub(crate) fn has_grpc(models: &InternalModels) -> bool {
+    models.iter().any(has_service)
+}
+
+/// A service only holds the authenticator if one of its rpcs needs a principal.
+fn service_needs_authenticator(model: &InternalModel) -> bool {
+    RPC_ENDPOINTS
+        .iter()
+        .filter(|endpoint| model.has_endpoint(**endpoint))
+        .any(|endpoint| !get_auth_code(&[(model, *endpoint)], None).param.is_empty())
+}
+
+pub(crate) fn grpc_needs_authenticator(models: &InternalModels) -> bool {
+    models.iter().filter(|model| has_service(model)).any(service_needs_authenticator)
+}
+
+/// Protobuf names are snake case, prost keeps them as rust field names.
+fn get_proto_field_name(field: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in field.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 && !name.ends_with('_') {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+fn get_proto_scalar(data_type: &RustDataType) -> &'static str {
+    match data_type {
+        RustDataType::U8 => "uint32",
+        RustDataType::I32 => "int32",
+        RustDataType::I64 => "int64",
+        RustDataType::String | RustDataType::Date | RustDataType::DateTime => "string",
+        RustDataType::Bool => "bool",
+        RustDataType::F32 => "float",
+        RustDataType::F64 => "double",
+    }
+}
+
+/// A `Vec<u8>` is sent as `bytes`, all other arrays as repeated fields.
+fn is_repeated(property: &Property) -> bool {
+    property.is_array && property.data_type != RustDataType::U8
+}
+
+fn is_option(property: &Property) -> bool {
+    get_rust_type(property).starts_with("Option<")
+}
+
+/// Request fields with a spec default are `optional`, so the default applies when they aren't sent.
+fn has_default(property: &Property, is_request: bool) -> bool {
+    is_request && !is_repeated(property) && get_rust_default_value(property).is_some()
+}
+
+fn get_proto_type(property: &Property, is_request: bool) -> String {
+    let scalar = if property.is_array && !is_repeated(property) {
+        "bytes"
+    } else {
+        get_proto_scalar(&property.data_type)
+    };
+    if is_repeated(property) {
+        format!("repeated {}", scalar)
+    } else if is_option(property) || has_default(property, is_request) {
+        format!("optional {}", scalar)
+    } else {
+        scalar.to_string()
+    }
+}
+
+fn get_id_data_type(model: &InternalModel) -> RustDataType {
+    model
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get("id"))
+        .map(|id| id.data_type)
+        .unwrap_or(RustDataType::I64)
+}
+
+/// A message with the `fields` as `(type, name)`, numbered in order.
+fn get_message_code(name: &str, fields: &[(String, String)]) -> String {
+    let fields: String = fields
+        .iter()
+        .enumerate()
+        .map(|(i, (proto_type, field))| format!("  {} {} = {};\n", proto_type, field, i + 1))
+        .collect();
+    format!("message {} {{\n{}}}\n\n", name, fields)
+}
+
+fn get_property_fields(properties: &[(&String, &Property)], is_request: bool) -> Vec<(String, String)> {
+    properties
+        .iter()
+        .map(|(key, property)| (get_proto_type(property, is_request), get_proto_field_name(property.column_name(key))))
+        .collect()
+}
+
+/// `proto/crudify.proto`: the model messages and a `{Model}Service` with the rpcs of the REST endpoints of each model.
+pub fn create_proto(models: &InternalModels) -> String {
+    let mut code = format!("// Generated by crudify, changes are overwritten.\n\nsyntax = \"proto3\";\n\npackage {};\n\n", PROTO_PACKAGE);
+    for model in models.iter().filter(|model| has_service(model)) {
+        let (readable, writable, updatable) = get_struct_properties(model);
+        let id = (get_proto_scalar(&get_id_data_type(model)).to_string(), "id".to_string());
+        let version = ("optional int64".to_string(), "version".to_string());
+        let versioned = model.options.versioned;
+        let mut rpcs = "".to_string();
+        code.push_str(&get_message_code(&model.name, &get_property_fields(&readable, false)));
+        if model.has_endpoint(Endpoint::List) {
+            let mut fields = vec![
+                ("int64".to_string(), "limit".to_string()),
+                ("int64".to_string(), "offset".to_string()),
+                ("string".to_string(), "cursor".to_string()),
+                ("repeated string".to_string(), "sort".to_string()),
+                ("map<string, string>".to_string(), "filters".to_string()),
+            ];
+            if model.options.soft_delete {
+                fields.push(("bool".to_string(), "include_deleted".to_string()));
+            }
+            code.push_str(&format!(
+                "// `filters` and the other fields are the query parameters of `GET /api/{}`, 0 and empty values are left out.\n",
+                model.name.to_lowercase()
+            ));
+            code.push_str(&get_message_code(&format!("List{}Request", model.name), &fields));
+            code.push_str(&get_message_code(
+                &format!("List{}Response", model.name),
+                &[
+                    (format!("repeated {}", model.name), "items".to_string()),
+                    ("int64".to_string(), "total".to_string()),
+                    ("optional string".to_string(), "next_cursor".to_string()),
+                ],
+            ));
+            rpcs.push_str(&format!("  rpc List(List{0}Request) returns (List{0}Response);\n", model.name));
+            if get_readable_id(model).is_some() {
+                code.push_str(&get_message_code(&format!("Get{}Request", model.name), std::slice::from_ref(&id)));
+                rpcs.push_str(&format!("  rpc Get(Get{0}Request) returns ({0});\n", model.name));
+            }
+        }
+        if model.has_endpoint(Endpoint::Create) {
+            code.push_str(&get_message_code(&format!("Create{}Request", model.name), &get_property_fields(&writable, true)));
+            rpcs.push_str(&format!("  rpc Create(Create{0}Request) returns ({0});\n", model.name));
+        }
+        if model.has_endpoint(Endpoint::Update) {
+            let mut fields = vec![id.clone()];
+            if versioned {
+                fields.push(version.clone());
+            }
+            fields.extend(get_property_fields(&updatable, true));
+            code.push_str(&get_message_code(&format!("Update{}Request", model.name), &fields));
+            rpcs.push_str(&format!("  rpc Update(Update{0}Request) returns ({0});\n", model.name));
+        }
+        if model.has_endpoint(Endpoint::Delete) {
+            let mut fields = vec![id.clone()];
+            if versioned {
+                fields.push(version.clone());
+            }
+            code.push_str(&get_message_code(&format!("Delete{}Request", model.name), &fields));
+            code.push_str(&get_message_code(&format!("Delete{}Response", model.name), &[]));
+            rpcs.push_str(&format!("  rpc Delete(Delete{0}Request) returns (Delete{0}Response);\n", model.name));
+        }
+        code.push_str(&format!("service {}Service {{\n{}}}\n\n", model.name, rpcs));
+    }
+
+    code
+}
+
+/// The generated function that parses a proto value into the rust type, if they differ.
+fn get_parse_fn(data_type: &RustDataType) -> Option<&'static str> {
+    match data_type {
+        RustDataType::U8 => Some("parse_u8"),
+        RustDataType::Date => Some("parse_date"),
+        RustDataType::DateTime => Some("parse_date_time"),
+        _ => None,
+    }
+}
+
+/// The function turning a rust value into the proto one, if they differ.
+fn get_to_proto_fn(data_type: &RustDataType) -> Option<&'static str> {
+    match data_type {
+        RustDataType::U8 => Some("u32::from"),
+        RustDataType::Date => Some("|value| value.to_string()"),
+        RustDataType::DateTime => Some("|value| value.to_rfc3339()"),
+        _ => None,
+    }
+}
+
+/// Expression for the struct field of `key` from the field of a proto `request`, `?` returns a parse error.
+fn get_from_proto_code(model_name: &str, key: &str, property: &Property) -> String {
+    let field = format!("request.{}", rust_identifier(&get_proto_field_name(property.column_name(key))));
+    let parse = if property.is_array && !is_repeated(property) {
+        None
+    } else {
+        get_parse_fn(&property.data_type)
+    };
+    if is_repeated(property) {
+        let value = match parse {
+            Some(parse) => format!("{}.into_iter().map(|value| {}(\"{}\", value)).collect::<Result<Vec<_>, _>>()?", field, parse, key),
+            None => field,
+        };
+        return if is_option(property) { format!("Some({})", value) } else { value };
+    }
+    let value = match (is_option(property) || has_default(property, true), parse) {
+        (true, Some(parse)) => format!("{}.map(|value| {}(\"{}\", value)).transpose()?", field, parse, key),
+        (false, Some(parse)) => format!("{}(\"{}\", {})?", parse, key, field),
+        (_, None) => field,
+    };
+    if !has_default(property, true) {
+        value
+    } else if is_option(property) {
+        format!("{}.or_else({})", value, get_default_fn_name(model_name, key))
+    } else {
+        format!("{}.unwrap_or_else({})", value, get_default_fn_name(model_name, key))
+    }
+}
+
+/// Expression for the proto field of `key` from the field of a model `row`.
+fn get_to_proto_code(key: &str, property: &Property) -> String {
+    let field = format!("row.{}", property.field_name(key));
+    if property.is_array && !is_repeated(property) {
+        return field;
+    }
+    let convert = get_to_proto_fn(&property.data_type);
+    if is_repeated(property) {
+        let value = if is_option(property) { format!("{}.unwrap_or_default()", field) } else { field };
+        return match convert {
+            Some(convert) => format!("{}.into_iter().map({}).collect()", value, convert),
+            None => value,
+        };
+    }
+    match (is_option(property), &property.data_type) {
+        (true, _) => match convert {
+            Some(convert) => format!("{}.map({})", field, convert),
+            None => field,
+        },
+        (false, RustDataType::U8) => format!("u32::from({})", field),
+        (false, RustDataType::Date) => format!("{}.to_string()", field),
+        (false, RustDataType::DateTime) => format!("{}.to_rfc3339()", field),
+        (false, _) => field,
+    }
+}
+
+/// The rust `id` of a proto `request`. If the request is still converted afterwards a string id is cloned out of it.
+fn get_id_code(model: &InternalModel, keep_request: bool) -> String {
+    let data_type = get_id_data_type(model);
+    let field = match data_type {
+        RustDataType::String | RustDataType::Date | RustDataType::DateTime if keep_request => "request.id.clone()",
+        _ => "request.id",
+    };
+    match get_parse_fn(&data_type) {
+        Some(parse) => format!("{}(\"id\", {})?", parse, field),
+        None => field.to_string(),
+    }
+}
+
+/// `From<{Model}> for proto::{Model}` and the request structs from the request messages.
+fn get_conversions_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let (readable, writable, updatable) = get_struct_properties(model);
+    let to_proto: String = readable
+        .iter()
+        .map(|(key, property)| {
+            format!("{}: {},\n", rust_identifier(&get_proto_field_name(property.column_name(key))), get_to_proto_code(key, property))
+        })
+        .collect();
+    let mut code = format!(
+        "\nimpl From<{0}> for proto::{0} {{\n    fn from(row: {0}) -> Self {{\n        proto::{0} {{\n{1}}}\n    }}\n}}\n",
+        model.name, to_proto
+    );
+    let requests = [
+        (Endpoint::Create, "Create", writable),
+        (Endpoint::Update, "Update", updatable),
+    ];
+    for (endpoint, prefix, properties) in requests {
+        if !model.has_endpoint(endpoint) {
+            continue;
+        }
+        let fields: String = properties
+            .iter()
+            .map(|(key, property)| format!("{}: {},\n", property.field_name(key), get_from_proto_code(&name, key, property)))
+            .collect();
+        code.push_str(&format!(
+            r#"
+impl TryFrom<proto::{0}{1}Request> for {0}{1} {{
+    type Error = AppError;
+
+    fn try_from(request: proto::{0}{1}Request) -> Result<Self, AppError> {{
+        Ok({0}{1} {{
+{2}}})
+    }}
+}}
+"#,
+            prefix, model.name, fields
+        ));
+    }
+
+    code
+}
+
+/// The rpcs run the checks and queries of the REST handlers, on a principal from the request metadata.
+fn get_rpc_fns_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let mut code = "".to_string();
+    if model.has_endpoint(Endpoint::List) {
+        let auth = get_auth_code(&[(model, Endpoint::List)], None);
+        let include_deleted = if model.options.soft_delete { "request.include_deleted" } else { "false" };
+        code.push_str(&format!(
+            r#"
+async fn rpc_list_{0}(pool: DbPool, {2}request: proto::List{1}Request) -> Result<proto::List{1}Response, AppError> {{
+    {3}{4}let params = list_params(request.limit, request.offset, request.cursor, request.sort, request.filters, {5});
+    let page: ListPage<{1}> = page_from_json(list_{0}(&mut conn, &params).await?)?;
+    Ok(proto::List{1}Response {{
+        items: page.items.into_iter().map(proto::{1}::from).collect(),
+        total: page.total,
+        next_cursor: page.next_cursor,
+    }})
+}}
+"#,
+            name,
+            model.name,
+            auth.param,
+            auth.checks,
+            auth.connect(),
+            include_deleted
+        ));
+        if get_readable_id(model).is_some() {
+            code.push_str(&format!(
+                r#"
+async fn rpc_get_{0}(pool: DbPool, {2}request: proto::Get{1}Request) -> Result<proto::{1}, AppError> {{
+    {3}{4}let params = vec![("id".to_string(), request.id.to_string())];
+    let page: ListPage<{1}> = page_from_json(list_{0}(&mut conn, &params).await?)?;
+    page.items.into_iter().next().map(proto::{1}::from).ok_or_else(|| AppError::new_not_found("Not found".to_string()))
+}}
+"#,
+                name,
+                model.name,
+                auth.param,
+                auth.checks,
+                auth.connect()
+            ));
+        }
+    }
+    if model.has_endpoint(Endpoint::Create) {
+        let auth = get_auth_code(&[(model, Endpoint::Create)], None);
+        code.push_str(&format!(
+            r#"
+async fn rpc_create_{0}(pool: DbPool, {2}request: proto::Create{1}Request) -> Result<proto::{1}, AppError> {{
+    {3}let {0} = Create{1}::try_from(request)?;
+    {4}let res = insert_{0}(&mut conn, &{0}).await?;
+    {5}Ok(res.into())
+}}
+"#,
+            name,
+            model.name,
+            auth.param,
+            auth.checks,
+            auth.connect(),
+            auth.commit()
+        ));
+    }
+    if model.has_endpoint(Endpoint::Update) {
+        let auth = get_auth_code(&[(model, Endpoint::Update)], None);
+        let (version, version_bind) = if model.options.versioned {
+            ("let version = request.version;\n", ".bind(version)")
+        } else {
+            ("", "")
+        };
+        let query = format!("sqlx::query_as::<_, {}>(query).bind(id){}{}", model.name, version_bind, get_update_binds_code(model));
+        code.push_str(&format!(
+            r#"
+async fn rpc_update_{0}(pool: DbPool, {2}request: proto::Update{1}Request) -> Result<proto::{1}, AppError> {{
+    {3}let id = {4};
+    {5}let {0} = Update{1}::try_from(request)?;
+    {6}let query = {7:?};
+    {8}
+    {9}Ok(res.into())
+}}
+"#,
+            name,
+            model.name,
+            auth.param,
+            auth.checks,
+            get_id_code(model, true),
+            version,
+            auth.connect(),
+            create_update_entity(model),
+            get_fetch_changed_code(model, "res", &query, "&mut *conn"),
+            auth.commit()
+        ));
+    }
+    if model.has_endpoint(Endpoint::Delete) {
+        let auth = get_auth_code(&[(model, Endpoint::Delete)], None);
+        let version = if model.options.versioned { "request.version, " } else { "" };
+        code.push_str(&format!(
+            r#"
+async fn rpc_delete_{0}(pool: DbPool, {2}request: proto::Delete{1}Request) -> Result<proto::Delete{1}Response, AppError> {{
+    {3}{4}delete_{0}_row(&mut conn, {5}{6}).await?;
+    {7}Ok(proto::Delete{1}Response {{}})
+}}
+"#,
+            name,
+            model.name,
+            auth.param,
+            auth.checks,
+            auth.connect(),
+            version,
+            get_id_code(model, false),
+            auth.commit()
+        ));
+    }
+
+    code
+}
+
+/// The `{Model}Service` of tonic, each rpc authenticates the request if it needs a principal.
+fn get_service_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let authenticator = if service_needs_authenticator(model) {
+        "    authenticator: std::sync::Arc<Authenticator>,\n"
+    } else {
+        ""
+    };
+    let mut rpcs = vec![];
+    if model.has_endpoint(Endpoint::List) {
+        rpcs.push((Endpoint::List, "list", format!("List{}Request", model.name), format!("List{}Response", model.name)));
+        if get_readable_id(model).is_some() {
+            rpcs.push((Endpoint::List, "get", format!("Get{}Request", model.name), model.name.to_string()));
+        }
+    }
+    if model.has_endpoint(Endpoint::Create) {
+        rpcs.push((Endpoint::Create, "create", format!("Create{}Request", model.name), model.name.to_string()));
+    }
+    if model.has_endpoint(Endpoint::Update) {
+        rpcs.push((Endpoint::Update, "update", format!("Update{}Request", model.name), model.name.to_string()));
+    }
+    if model.has_endpoint(Endpoint::Delete) {
+        rpcs.push((Endpoint::Delete, "delete", format!("Delete{}Request", model.name), format!("Delete{}Response", model.name)));
+    }
+    let methods: String = rpcs
+        .iter()
+        .map(|(endpoint, rpc, request, response)| {
+            let principal = if get_auth_code(&[(model, *endpoint)], None).param.is_empty() {
+                ""
+            } else {
+                "principal(&self.authenticator, &request).map_err(status)?, "
+            };
+            format!(
+                r#"
+    async fn {0}(&self, request: Request<proto::{2}>) -> Result<Response<proto::{3}>, Status> {{
+        rpc_{0}_{1}(self.pool.clone(), {4}request.into_inner()).await.map(Response::new).map_err(status)
+    }}
+"#,
+                rpc, name, request, response, principal
+            )
+        })
+        .collect();
+
+    format!(
+        "\nstruct {0}Grpc {{\n    pool: DbPool,\n{1}}}\n\n#[tonic::async_trait]\nimpl proto::{2}_server::{0}Service for {0}Grpc {{{3}}}\n",
+        model.name,
+        authenticator,
+        get_proto_field_name(&format!("{}Service", model.name)),
+        methods
+    )
+}
+
+/// `src/grpc.rs` of the server: the code tonic generates from the proto file, the conversions between the messages and
+/// the model structs, and the services of all models.
+pub fn create_grpc_service(models: &InternalModels, backend: &dyn WebBackend) -> String {
+    let services: Vec<&InternalModel> = models.iter().filter(|model| has_service(model)).collect();
+    let mut code = format!(
+        "// Generated by crudify, changes are overwritten.\n\nuse tonic::{{Request, Response, Status}};\n\nuse super::*;\n\npub mod proto {{\n    tonic::include_proto!(\"{}\");\n}}\n",
+        PROTO_PACKAGE
+    );
+    code.push_str(STATUS_FN);
+    if grpc_needs_authenticator(models) {
+        // the metadata of a request holds the same `authorization` or `x-api-key` headers as a REST request
+        code.push_str(&format!(
+            r#"
+fn principal<T>(authenticator: &Authenticator, request: &Request<T>) -> Result<Principal, AppError> {{
+    authenticator.authenticate(&{})
+}}
+"#,
+            backend.headers_from_http("request.metadata().clone().into_headers()")
+        ));
+    }
+    if services.iter().any(|model| model.has_endpoint(Endpoint::List)) {
+        code.push_str(LIST_FNS);
+    }
+    // only the parse functions the conversions call, an unused one would be a warning in the generated project
+    let parsed: Vec<RustDataType> = services
+        .iter()
+        .flat_map(|model| {
+            let (_, writable, _) = get_struct_properties(model);
+            let id = RPC_ENDPOINTS[1..]
+                .iter()
+                .any(|endpoint| model.has_endpoint(*endpoint))
+                .then(|| get_id_data_type(model));
+            writable
+                .into_iter()
+                .filter(|(_, property)| !property.is_array || is_repeated(property))
+                .map(|(_, property)| property.data_type)
+                .chain(id)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for (data_type, parse_fn) in [
+        (RustDataType::U8, PARSE_U8_FN),
+        (RustDataType::Date, PARSE_DATE_FN),
+        (RustDataType::DateTime, PARSE_DATE_TIME_FN),
+    ] {
+        if parsed.contains(&data_type) {
+            code.push_str(parse_fn);
+        }
+    }
+    for model in &services {
+        code.push_str(&get_conversions_code(model));
+        code.push_str(&get_rpc_fns_code(model));
+        code.push_str(&get_service_code(model));
+    }
+    let (param, authenticator) = if grpc_needs_authenticator(models) {
+        (", authenticator: std::sync::Arc<Authenticator>", ", authenticator: authenticator.clone()")
+    } else {
+        ("", "")
+    };
+    let add_services: String = services
+        .iter()
+        .map(|model| {
+            format!(
+                "\n        .add_service(proto::{0}_server::{1}ServiceServer::new({1}Grpc {{ pool: pool.clone(){2} }}))",
+                get_proto_field_name(&format!("{}Service", model.name)),
+                model.name,
+                if service_needs_authenticator(model) { authenticator } else { "" }
+            )
+        })
+        .collect();
+    code.push_str(&format!(
+        "\n/// The services of all models, on the pool and with the credentials of the REST api.\npub fn server(pool: DbPool{}) -> tonic::transport::server::Router {{\n    tonic::transport::Server::builder(){}\n}}\n",
+        param, add_services
+    ));
+
+    code
+}
+
+/// Errors are answered with the gRPC code closest to the http status of the REST answer.
+const STATUS_FN: &str = r#"
+fn status(error: AppError) -> Status {
+    let code = match error.status_code {
+        400 => tonic::Code::InvalidArgument,
+        401 => tonic::Code::Unauthenticated,
+        403 => tonic::Code::PermissionDenied,
+        404 => tonic::Code::NotFound,
+        409 => tonic::Code::AlreadyExists,
+        412 | 428 => tonic::Code::FailedPrecondition,
+        _ => tonic::Code::Internal,
+    };
+    Status::new(code, error.errors.join(", "))
+}
+"#;
+
+const LIST_FNS: &str = r#"
+#[derive(Deserialize)]
+struct ListPage<T> {
+    items: Vec<T>,
+    total: i64,
+    next_cursor: Option<String>,
+}
+
+fn page_from_json<T: serde::de::DeserializeOwned>(page: Value) -> Result<ListPage<T>, AppError> {
+    serde_json::from_value(page).map_err(|e| AppError::new_internal(e.to_string()))
+}
+
+/// The query parameters of the REST list request, proto3 can't tell unset fields from 0 and empty ones.
+fn list_params(
+    limit: i64,
+    offset: i64,
+    cursor: String,
+    sort: Vec<String>,
+    filters: std::collections::HashMap<String, String>,
+    include_deleted: bool,
+) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = filters.into_iter().collect();
+    if limit > 0 {
+        params.push(("limit".to_string(), limit.to_string()));
+    }
+    if offset > 0 {
+        params.push(("offset".to_string(), offset.to_string()));
+    }
+    if !cursor.is_empty() {
+        params.push(("cursor".to_string(), cursor));
+    }
+    if !sort.is_empty() {
+        params.push(("sort".to_string(), sort.join(",")));
+    }
+    if include_deleted {
+        params.push(("include_deleted".to_string(), "true".to_string()));
+    }
+    params
+}
+"#;
+
+const PARSE_U8_FN: &str = r#"
+fn parse_u8(key: &str, value: u32) -> Result<u8, AppError> {
+    u8::try_from(value).map_err(|_| AppError::new_bad_request(format!("Invalid value for {}: {} is out of range", key, value)))
+}
+"#;
+
+const PARSE_DATE_FN: &str = r#"
+fn parse_date(key: &str, value: String) -> Result<chrono::NaiveDate, AppError> {
+    value.parse().map_err(|e| AppError::new_bad_request(format!("Invalid value for {}: {}", key, e)))
+}
+"#;
+
+const PARSE_DATE_TIME_FN: &str = r#"
+fn parse_date_time(key: &str, value: String) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    chrono::DateTime::parse_from_rfc3339(&value)
+        .map(|value| value.with_timezone(&chrono::Utc))
+        .map_err(|e| AppError::new_bad_request(format!("Invalid value for {}: {}", key, e)))
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+    use serde_json::json;
+
+    use crate::{Access, ModelOptions};
+    use crate::web_backend::Axum;
+
+    use super::*;
+
+    fn models() -> InternalModels {
+        let mut id = Property::new(RustDataType::I64);
+        id.read_only = true;
+        let mut name = Property::new(RustDataType::String);
+        name.required = true;
+        let mut born = Property::new(RustDataType::Date);
+        born.required = true;
+        let mut tags = Property::new(RustDataType::String);
+        tags.is_array = true;
+        let mut status = Property::new(RustDataType::String);
+        status.default = Some(json!("available"));
+        let pet = InternalModel {
+            name: "Pet".to_string(),
+            description: None,
+            properties: Some(indexmap! {
+                "id".to_string() => id.clone(),
+                "name".to_string() => name.clone(),
+                "bornOn".to_string() => born,
+                "tags".to_string() => tags,
+                "status".to_string() => status,
+            }),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions {
+                soft_delete: true,
+                grpc: true,
+                ..ModelOptions::default()
+            },
+        };
+        let category = InternalModel {
+            name: "Category".to_string(),
+            description: None,
+            properties: Some(indexmap! {"id".to_string() => id, "name".to_string() => name}),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions {
+                versioned: true,
+                grpc: true,
+                access: Some(Access {
+                    create: vec!["admin".to_string()],
+                    ..Access::default()
+                }),
+                ..ModelOptions::default()
+            },
+        };
+        vec![pet, category]
+    }
+
+    #[test]
+    fn test_proto_messages_and_services() {
+        let proto = create_proto(&models());
+        assert!(proto.contains("syntax = \"proto3\";\n\npackage crudify;"));
+        assert!(proto.contains("message Pet {\n  optional int64 id = 1;\n  string name = 2;\n  string born_on = 3;\n  repeated string tags = 4;"));
+        assert!(proto.contains("message CreatePetRequest {\n  string name = 1;\n  string born_on = 2;\n  repeated string tags = 3;\n  optional string status = 4;\n}"));
+        assert!(proto.contains("  map<string, string> filters = 5;\n  bool include_deleted = 6;\n}"));
+        assert!(proto.contains("message UpdateCategoryRequest {\n  int64 id = 1;\n  optional int64 version = 2;\n  string name = 3;\n}"));
+        assert!(proto.contains("service PetService {\n  rpc List(ListPetRequest) returns (ListPetResponse);\n  rpc Get(GetPetRequest) returns (Pet);"));
+        assert!(proto.contains("  rpc Delete(DeleteCategoryRequest) returns (DeleteCategoryResponse);\n}"));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let code = create_grpc_service(&models(), &Axum);
+        assert!(code.contains("impl From<Pet> for proto::Pet {"));
+        assert!(code.contains("born_on: row.bornOn.to_string(),"));
+        assert!(code.contains("impl TryFrom<proto::CreatePetRequest> for CreatePet {"));
+        assert!(code.contains("bornOn: parse_date(\"bornOn\", request.born_on)?,"));
+        assert!(code.contains("status: request.status.or_else(default_pet_status),"));
+        assert!(code.contains("fn parse_date(key: &str, value: String)"));
+        assert!(!code.contains("fn parse_date_time("));
+    }
+
+    #[test]
+    fn test_services_and_server() {
+        let models = models();
+        assert!(grpc_needs_authenticator(&models));
+        let code = create_grpc_service(&models, &Axum);
+        assert!(code.contains("tonic::include_proto!(\"crudify\");"));
+        assert!(code.contains("impl proto::pet_service_server::PetService for PetGrpc {"));
+        assert!(code.contains("rpc_list_pet(self.pool.clone(), request.into_inner()).await.map(Response::new).map_err(status)"));
+        assert!(code.contains("rpc_create_category(self.pool.clone(), principal(&self.authenticator, &request).map_err(status)?, request.into_inner())"));
+        assert!(code.contains("let version = request.version;"));
+        assert!(code.contains("delete_category_row(&mut conn, request.version, request.id).await?;"));
+        assert!(code.contains(".add_service(proto::pet_service_server::PetServiceServer::new(PetGrpc { pool: pool.clone() }))"));
+        assert!(code.contains("CategoryServiceServer::new(CategoryGrpc { pool: pool.clone(), authenticator: authenticator.clone() })"));
+    }
+
+    #[test]
+    fn test_string_id() {
+        let mut id = Property::new(RustDataType::String);
+        id.format = Some("uuid".to_string());
+        let tag = InternalModel {
+            name: "Tag".to_string(),
+            description: None,
+            properties: Some(indexmap! {"id".to_string() => id}),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions {
+                grpc: true,
+                ..ModelOptions::default()
+            },
+        };
+        let code = create_grpc_service(&vec![tag], &Axum);
+        assert!(code.contains("let id = request.id.clone();\n    let tag = UpdateTag::try_from(request)?;"));
+        assert!(code.contains("delete_tag_row(&mut conn, request.id).await?;"));
+    }
+
+    #[test]
+    fn test_opt_in() {
+        let mut models = models();
+        models[1].options.grpc = false;
+        assert!(has_grpc(&models));
+        assert!(!grpc_needs_authenticator(&models));
+        assert!(!create_proto(&models).contains("CategoryService"));
+
+        models[0].options.grpc = false;
+        assert!(!has_grpc(&models));
+    }
+}
@@ -0,0 +1,344 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::json_converter::RustDataType;
+
+/// Intermediate representation of one schema object from the spec. Everything the generators need to know about a model
+/// lives here, so the IR can be dumped to json, cached and diffed between generator runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InternalModel {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub properties: Option<IndexMap<String, Property>>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+    #[serde(default)]
+    pub indexes: Vec<Index>,
+    #[serde(default)]
+    pub options: ModelOptions,
+}
+
+impl InternalModel {
+    pub fn has_endpoint(&self, endpoint: Endpoint) -> bool {
+        !self.options.skip_endpoints.contains(&endpoint)
+    }
+}
+
+pub type InternalModels = Vec<InternalModel>;
+
+/// Generation knobs for a model that the OpenAPI standard doesn't cover, set via `x-crudify-*` extensions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelOptions {
+    /// Overrides the table name, which defaults to the lowercased model name in the `public` schema.
+    #[serde(default)]
+    pub table_name: Option<String>,
+    #[serde(default)]
+    pub skip_endpoints: Vec<Endpoint>,
+    /// Deleting only sets the `deleted_at` property and hides the row from reads.
+    #[serde(default)]
+    pub soft_delete: bool,
+    /// Rows carry the `created_at` and `updated_at` properties, which are maintained by the generated queries.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// Every change of a row is recorded in a `{table}_history` table by a trigger.
+    #[serde(default)]
+    pub history: bool,
+    /// Rows carry a `version` that is sent as `ETag` and has to be matched by `If-Match` to change the row.
+    #[serde(default)]
+    pub versioned: bool,
+    /// Columns of the unique key a `PUT /api/{model}` inserts or updates a row by, no upsert endpoint if `None`.
+    #[serde(default)]
+    pub upsert_key: Option<Vec<String>>,
+    /// The text search configuration the searchable properties are parsed with, `simple` if not set.
+    #[serde(default)]
+    pub search_config: Option<String>,
+    /// Who may call the endpoints, everyone if `None`.
+    #[serde(default)]
+    pub access: Option<Access>,
+    /// Rows belong to a tenant or an owner and are only seen by principals of it.
+    #[serde(default)]
+    pub tenancy: Option<Tenancy>,
+    /// The model is part of the GraphQL schema served at `/graphql`.
+    #[serde(default)]
+    pub graphql: bool,
+    /// The model has a service on the gRPC server next to the REST api.
+    #[serde(default)]
+    pub grpc: bool,
+}
+
+/// Who a row belongs to. The column is filled from the transaction setting the handlers set from the principal, and
+/// every query only sees the rows matching it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tenancy {
+    /// The tenant of the principal, `tenant_id`.
+    Tenant,
+    /// The principal itself, `owner_id`.
+    Owner,
+}
+
+impl Tenancy {
+    pub fn column(&self) -> &'static str {
+        match self {
+            Tenancy::Tenant => "tenant_id",
+            Tenancy::Owner => "owner_id",
+        }
+    }
+
+    pub fn setting(&self) -> &'static str {
+        match self {
+            Tenancy::Tenant => "crudify.tenant",
+            Tenancy::Owner => "crudify.owner",
+        }
+    }
+
+    /// The sql expression for the tenant or owner of the transaction, `NULL` if it isn't set so no row matches.
+    pub fn current_value(&self) -> String {
+        format!("nullif(current_setting('{}', true), '')", self.setting())
+    }
+}
+
+/// Roles a principal needs per endpoint, one of them is enough. An endpoint without roles is open to every
+/// authenticated principal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Access {
+    #[serde(default)]
+    pub list: Vec<String>,
+    #[serde(default)]
+    pub create: Vec<String>,
+    #[serde(default)]
+    pub update: Vec<String>,
+    #[serde(default)]
+    pub delete: Vec<String>,
+}
+
+impl Access {
+    pub fn roles(&self, endpoint: Endpoint) -> &[String] {
+        match endpoint {
+            Endpoint::List => &self.list,
+            Endpoint::Create => &self.create,
+            Endpoint::Update => &self.update,
+            Endpoint::Delete => &self.delete,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+    List,
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Property {
+    pub data_type: RustDataType,
+    /// The original OpenAPI format, i.e. `password` or `date-time`.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// `type: array` with `items` of `data_type`.
+    #[serde(default)]
+    pub is_array: bool,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub nullable: bool,
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// A sql expression the database fills in when no value is given, i.e. `now()`.
+    #[serde(default)]
+    pub server_default: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub example: Option<Value>,
+    #[serde(default)]
+    pub enum_values: Option<Vec<Value>>,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub write_only: bool,
+    #[serde(default)]
+    pub constraints: Constraints,
+    #[serde(default)]
+    pub storage: StorageHints,
+    /// Part of the full text search of the model.
+    #[serde(default)]
+    pub searchable: bool,
+}
+
+impl Property {
+    pub fn new(data_type: RustDataType) -> Property {
+        Property {
+            data_type,
+            format: None,
+            is_array: false,
+            required: false,
+            nullable: false,
+            default: None,
+            server_default: None,
+            description: None,
+            example: None,
+            enum_values: None,
+            read_only: false,
+            write_only: false,
+            constraints: Constraints::default(),
+            storage: StorageHints::default(),
+            searchable: false,
+        }
+    }
+
+    /// A property that may be missing or null ends up as an `Option` in rust and as a nullable column in sql.
+    pub fn is_optional(&self) -> bool {
+        !self.required || self.nullable
+    }
+
+    pub fn has_default(&self) -> bool {
+        self.default.is_some() || self.server_default.is_some()
+    }
+
+    /// Whether the property is ever sent back to a client. `writeOnly` and `password` properties never leave the database.
+    pub fn is_readable(&self) -> bool {
+        !self.write_only && self.format.as_deref() != Some("password")
+    }
+
+    /// Whether a client may set the property, `readOnly` properties are filled in by the server.
+    pub fn is_writable(&self) -> bool {
+        !self.read_only
+    }
+
+    /// The name of the column, `key` is the name of the property in the spec and the api.
+    pub fn column_name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.storage.column_name.as_deref().unwrap_or(key)
+    }
+
+    /// The rust field of the property, named like the column.
+    pub fn field_name(&self, key: &str) -> String {
+        rust_identifier(self.column_name(key))
+    }
+}
+
+/// Keywords a field can't be named even as raw identifier.
+pub const UNNAMABLE_FIELDS: [&str; 4] = ["crate", "self", "Self", "super"];
+
+const RUST_KEYWORDS: [&str; 47] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move",
+    "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `name` as rust identifier, a keyword like `type` becomes the raw identifier `r#type`. serde and sqlx strip the `r#`,
+/// so the json key and the column stay `type`.
+pub fn rust_identifier(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Constraints {
+    pub min_length: Option<u64>,
+    pub max_length: Option<u64>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub exclusive_minimum: bool,
+    pub exclusive_maximum: bool,
+    pub pattern: Option<String>,
+    pub min_items: Option<u64>,
+    pub max_items: Option<u64>,
+}
+
+/// How a property is stored in the database, if it differs from what the name and data type suggest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageHints {
+    pub column_name: Option<String>,
+    pub sql_type: Option<String>,
+}
+
+/// A property that points to another model via `$ref` instead of holding a value itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    /// Name of the property in the spec, i.e. `category` for `Pet.category`.
+    pub name: String,
+    /// Name of the referenced model, i.e. `Category`.
+    pub target: String,
+    pub kind: RelationKind,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The property holding the id of the other side, on this model for `BelongsTo` and on the target for `HasMany`.
+    /// `None` if the side that is referred to has no `id`.
+    #[serde(default)]
+    pub foreign_key: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelationKind {
+    /// A single `$ref`, the model holds a reference to one target.
+    BelongsTo,
+    /// An array of `$ref`, the model has many targets.
+    HasMany,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub unique: bool,
+    /// Condition for a partial index, i.e. `deleted_at IS NULL`.
+    #[serde(default)]
+    pub predicate: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use super::*;
+
+    #[test]
+    fn test_internal_model_json_roundtrip() {
+        let mut name = Property::new(RustDataType::String);
+        name.required = true;
+        name.default = Some(Value::String("doggie".to_string()));
+        let model = InternalModel {
+            name: "Pet".to_string(),
+            description: None,
+            properties: Some(indexmap! {"id".to_string() => Property::new(RustDataType::I64), "name".to_string() => name}),
+            relations: vec![Relation {
+                name: "category".to_string(),
+                target: "Category".to_string(),
+                kind: RelationKind::BelongsTo,
+                required: false,
+                description: None,
+                foreign_key: Some("category_id".to_string()),
+            }],
+            indexes: vec![],
+            options: ModelOptions::default(),
+        };
+
+        let json = serde_json::to_string(&model).unwrap();
+        let parsed: InternalModel = serde_json::from_str(&json).unwrap();
+        assert_eq!(model, parsed);
+        assert_eq!(
+            vec!["id", "name"],
+            parsed.properties.unwrap().keys().map(|k| k.as_str()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_partial_options_use_defaults() {
+        let parsed: InternalModel =
+            serde_json::from_str(r#"{"name": "Pet", "properties": null, "options": {"versioned": true}}"#).unwrap();
+        assert_eq!(ModelOptions { versioned: true, ..ModelOptions::default() }, parsed.options);
+    }
+}
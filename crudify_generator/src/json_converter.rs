@@ -1,85 +1,579 @@
-use std::{fmt::Display, u8};
+use std::fmt::Display;
 
 use indexmap::IndexMap;
+use log::warn;
 use phf::phf_map;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-use crate::{errors::JsonConverterError, errors::JsonConverterError::AsObjectError, InternalModel, InternalModels};
+use crate::{
+    errors::JsonConverterError,
+    errors::JsonConverterError::{AsObjectError, InvalidUpsertKey, UnknownIndexColumn, UnnamableProperty, UnsearchableProperty},
+    internal_model::UNNAMABLE_FIELDS,
+    Access, Constraints, Endpoint, Index, InternalModel, InternalModels,
+    ModelOptions, Property, Relation, RelationKind, StorageHints, Tenancy,
+};
+
+/// `x-crudify-*` extensions understood on a schema object, see the README for what they do.
+const MODEL_EXTENSIONS: [&str; 13] = [
+    "x-crudify-table",
+    "x-crudify-skip-endpoints",
+    "x-crudify-soft-delete",
+    "x-crudify-indexes",
+    "x-crudify-versioned",
+    "x-crudify-timestamps",
+    "x-crudify-history",
+    "x-crudify-upsert",
+    "x-crudify-search-config",
+    "x-crudify-roles",
+    "x-crudify-tenancy",
+    "x-crudify-graphql",
+    "x-crudify-grpc",
+];
+
+/// `x-crudify-*` extensions understood on a property.
+const PROPERTY_EXTENSIONS: [&str; 6] = [
+    "x-crudify-server-default",
+    "x-crudify-rename",
+    "x-crudify-column-type",
+    "x-crudify-index",
+    "x-crudify-unique",
+    "x-crudify-searchable",
+];
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OA3Kind {
+    Single(String),
+    // OpenAPI 3.1 style, i.e. `["string", "null"]`
+    Multiple(Vec<String>),
+}
+
+/// `exclusiveMinimum` and `exclusiveMaximum` are flags for `minimum` and `maximum` in OpenAPI 3.0, and the bound itself in
+/// OpenAPI 3.1, i.e. `"exclusiveMinimum": 5`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(untagged)]
+enum OA3Bound {
+    Flag(bool),
+    Value(f64),
+}
+
+/// The bound and whether it is exclusive, an OpenAPI 3.1 exclusive bound is used instead of the inclusive one.
+fn get_bound(inclusive: Option<f64>, exclusive: Option<OA3Bound>) -> (Option<f64>, bool) {
+    match exclusive {
+        Some(OA3Bound::Value(value)) => (Some(value), true),
+        Some(OA3Bound::Flag(flag)) => (inclusive, flag),
+        None => (inclusive, false),
+    }
+}
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct OA3Type {
     #[serde(rename = "type")]
-    kind: String,
+    kind: Option<OA3Kind>,
     format: Option<String>,
+    #[serde(rename = "$ref")]
+    reference: Option<String>,
+    items: Option<Box<OA3Type>>,
+    #[serde(default)]
+    nullable: bool,
+    default: Option<Value>,
+    #[serde(rename = "x-crudify-server-default")]
+    server_default: Option<String>,
+    description: Option<String>,
+    example: Option<Value>,
+    #[serde(rename = "enum")]
+    enum_values: Option<Vec<Value>>,
+    #[serde(default)]
+    read_only: bool,
+    #[serde(default)]
+    write_only: bool,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<OA3Bound>,
+    exclusive_maximum: Option<OA3Bound>,
+    pattern: Option<String>,
+    min_items: Option<u64>,
+    max_items: Option<u64>,
+    #[serde(rename = "x-crudify-rename")]
+    rename: Option<String>,
+    #[serde(rename = "x-crudify-column-type")]
+    column_type: Option<String>,
+    #[serde(rename = "x-crudify-index", default)]
+    index: bool,
+    #[serde(rename = "x-crudify-unique", default)]
+    unique: bool,
+    #[serde(rename = "x-crudify-searchable", default)]
+    searchable: bool,
 }
 
 impl OA3Type {
-    fn get_format_or_type(&self) -> String {
-        match &self.format {
-            Some(format) => format.to_string(),
-            None => self.kind.to_string(),
+    fn get_format_or_type(&self) -> Option<String> {
+        match (&self.format, &self.kind) {
+            (Some(format), _) => Some(format.to_string()),
+            (None, Some(OA3Kind::Single(kind))) => Some(kind.to_string()),
+            (None, Some(OA3Kind::Multiple(kinds))) => kinds.iter().find(|k| *k != "null").map(|k| k.to_string()),
+            (None, None) => None,
         }
     }
+
+    fn is_array(&self) -> bool {
+        self.get_format_or_type().as_deref() == Some("array")
+    }
+
+    fn is_nullable(&self) -> bool {
+        self.nullable || matches!(&self.kind, Some(OA3Kind::Multiple(kinds)) if kinds.iter().any(|k| k == "null"))
+    }
+
+    /// The name of the referenced model, `#/components/schemas/Category` references `Category`.
+    fn get_reference_target(&self) -> Option<String> {
+        self.reference
+            .as_ref()
+            .map(|reference| reference.rsplit('/').next().unwrap_or(reference).to_string())
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct OA3Object {
+    #[serde(default)]
+    required: Vec<String>,
+    description: Option<String>,
+    #[serde(rename = "x-crudify-table")]
+    table_name: Option<String>,
+    #[serde(rename = "x-crudify-skip-endpoints", default)]
+    skip_endpoints: Vec<Endpoint>,
+    #[serde(rename = "x-crudify-soft-delete", default)]
+    soft_delete: bool,
+    #[serde(rename = "x-crudify-indexes", default)]
+    indexes: Vec<OA3Index>,
+    #[serde(rename = "x-crudify-versioned", default)]
+    versioned: bool,
+    #[serde(rename = "x-crudify-timestamps", default)]
+    timestamps: bool,
+    #[serde(rename = "x-crudify-history", default)]
+    history: bool,
+    #[serde(rename = "x-crudify-upsert")]
+    upsert: Option<OA3Upsert>,
+    #[serde(rename = "x-crudify-search-config")]
+    search_config: Option<String>,
+    /// Security requirements like on an operation, `[{"bearer": ["admin"]}]`.
+    security: Option<Vec<IndexMap<String, Vec<String>>>>,
+    #[serde(rename = "x-crudify-roles")]
+    roles: Option<OA3Roles>,
+    #[serde(rename = "x-crudify-tenancy")]
+    tenancy: Option<Tenancy>,
+    #[serde(rename = "x-crudify-graphql", default)]
+    graphql: bool,
+    #[serde(rename = "x-crudify-grpc", default)]
+    grpc: bool,
+}
+
+/// The roles of all endpoints, or of single ones by endpoint name.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OA3Roles {
+    All(Vec<String>),
+    PerEndpoint(OA3EndpointRoles),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct OA3EndpointRoles {
+    list: Option<Vec<String>>,
+    create: Option<Vec<String>>,
+    update: Option<Vec<String>>,
+    delete: Option<Vec<String>>,
+}
+
+/// `true` upserts by `id`, an array of property names by that unique key.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OA3Upsert {
+    Enabled(bool),
+    Key(Vec<String>),
+}
+
+#[derive(Deserialize, Debug)]
+struct OA3Index {
+    name: Option<String>,
+    columns: Vec<String>,
+    #[serde(default)]
+    unique: bool,
+    #[serde(rename = "where")]
+    predicate: Option<String>,
+}
+
+struct ParsedProperties {
+    properties: IndexMap<String, Property>,
+    relations: Vec<Relation>,
+    indexes: Vec<Index>,
 }
 
-pub fn convert_to_internal_model(j: &Value) -> Result<InternalModels, JsonConverterError> {
+pub fn convert_to_internal_model(j: &Value) -> Result<InternalModels, JsonConverterError<'_>> {
     let mut internal_models = Vec::new();
     for (key, value) in as_object(j)? {
         if value.is_object() {
-            let properties = parse_properties(value)?;
+            warn_about_unknown_extensions(key, as_object(value)?, &MODEL_EXTENSIONS);
+            let object: OA3Object = serde_json::from_value(value.to_owned()).map_err(|_| AsObjectError(value))?;
+            let mut parsed = parse_properties(key, value, &object.required)?;
+            if object.versioned {
+                parsed.properties.insert("version".to_string(), get_version_property());
+            }
+            if object.timestamps {
+                parsed.properties.insert("created_at".to_string(), get_timestamp_property(true));
+                parsed.properties.insert("updated_at".to_string(), get_timestamp_property(true));
+            }
+            if object.soft_delete {
+                parsed.properties.insert("deleted_at".to_string(), get_timestamp_property(false));
+            }
+            if let Some(tenancy) = object.tenancy {
+                parsed.properties.insert(tenancy.column().to_string(), get_tenancy_property(tenancy));
+                parsed.indexes.push(Index {
+                    name: None,
+                    columns: vec![tenancy.column().to_string()],
+                    unique: false,
+                    predicate: None,
+                });
+            }
+            for index in object.indexes {
+                let index = parse_index(key, &parsed.properties, index)?;
+                parsed.indexes.push(index);
+            }
+            let upsert_key = match object.upsert {
+                None | Some(OA3Upsert::Enabled(false)) => None,
+                Some(OA3Upsert::Enabled(true)) => Some(parse_upsert_key(key, &mut parsed, vec!["id".to_string()])?),
+                Some(OA3Upsert::Key(columns)) => Some(parse_upsert_key(key, &mut parsed, columns)?),
+            };
 
             internal_models.push(InternalModel {
                 name: key.to_string(),
-                properties: Some(properties),
+                description: object.description,
+                properties: Some(parsed.properties),
+                relations: parsed.relations,
+                indexes: parsed.indexes,
+                options: ModelOptions {
+                    table_name: object.table_name,
+                    skip_endpoints: object.skip_endpoints,
+                    soft_delete: object.soft_delete,
+                    versioned: object.versioned,
+                    timestamps: object.timestamps,
+                    history: object.history,
+                    upsert_key,
+                    search_config: object.search_config,
+                    // only a principal can be scoped to its rows
+                    access: parse_access(object.security, object.roles).or_else(|| object.tenancy.map(|_| Access::default())),
+                    tenancy: object.tenancy,
+                    graphql: object.graphql,
+                    grpc: object.grpc,
+                },
             })
         }
     }
+    link_relations(&mut internal_models);
 
     Ok(internal_models)
 }
 
-fn parse_properties(value: &Value) -> Result<IndexMap<String, RustDataType>, JsonConverterError> {
-    let mut property_map: IndexMap<String, RustDataType> = IndexMap::new();
+fn parse_properties<'a>(model_name: &str, value: &'a Value, required: &[String]) -> Result<ParsedProperties, JsonConverterError<'a>> {
+    let mut parsed = ParsedProperties {
+        properties: IndexMap::new(),
+        relations: Vec::new(),
+        indexes: Vec::new(),
+    };
     let o = as_object(value)?;
     if let Some(properties) = o.get("properties") {
         for (property_key, property_value) in as_object_with_context(properties, value)? {
-            if property_value.is_object() {
-                let data_type = parse_data_type(property_value)?;
-                property_map.insert(property_key.to_string(), data_type);
+            if let Some(property_object) = property_value.as_object() {
+                warn_about_unknown_extensions(&format!("{}.{}", model_name, property_key), property_object, &PROPERTY_EXTENSIONS);
+                let oa3_type = parse_oa3_type(property_value)?;
+                let is_required = required.contains(property_key);
+                if let Some(relation) = parse_relation(property_key, &oa3_type, is_required) {
+                    parsed.relations.push(relation);
+                } else {
+                    let (index, unique) = (oa3_type.index, oa3_type.unique);
+                    let property = parse_property(property_value, oa3_type, is_required)?;
+                    if property.searchable && (property.data_type != RustDataType::String || property.is_array) {
+                        return Err(UnsearchableProperty(model_name.to_string(), property_key.to_string()));
+                    }
+                    if UNNAMABLE_FIELDS.contains(&property.column_name(property_key)) {
+                        return Err(UnnamableProperty(model_name.to_string(), property_key.to_string()));
+                    }
+                    if index || unique {
+                        parsed.indexes.push(Index {
+                            name: None,
+                            columns: vec![property.column_name(property_key).to_string()],
+                            unique,
+                            predicate: None,
+                        });
+                    }
+                    parsed.properties.insert(property_key.to_string(), property);
+                }
             } else {
                 return Err(AsObjectError(property_value));
             }
         }
     }
 
-    Ok(property_map)
+    Ok(parsed)
+}
+
+/// The `version` of a versioned model, only the server changes it.
+fn get_version_property() -> Property {
+    let mut version = Property::new(RustDataType::I64);
+    version.required = true;
+    version.read_only = true;
+    version.default = Some(Value::from(1));
+    version.description = Some("Incremented on every change, sent as ETag".to_string());
+    version
+}
+
+/// The `tenant_id` or `owner_id` of a row, taken from the principal that created it.
+fn get_tenancy_property(tenancy: Tenancy) -> Property {
+    let mut property = Property::new(RustDataType::String);
+    property.required = true;
+    property.read_only = true;
+    property.server_default = Some(tenancy.current_value());
+    property
+}
+
+/// A `created_at`, `updated_at` or `deleted_at` property, only the server sets them.
+fn get_timestamp_property(required: bool) -> Property {
+    let mut timestamp = Property::new(RustDataType::DateTime);
+    timestamp.required = required;
+    timestamp.read_only = true;
+    if required {
+        timestamp.server_default = Some("now()".to_string());
+    }
+    timestamp
+}
+
+/// Index columns are given as property names and stored as column names.
+fn parse_index<'a>(model_name: &str, properties: &IndexMap<String, Property>, index: OA3Index) -> Result<Index, JsonConverterError<'a>> {
+    let columns = index
+        .columns
+        .iter()
+        .map(|column| match properties.get(column) {
+            Some(property) => Ok(property.column_name(column).to_string()),
+            None => Err(UnknownIndexColumn(model_name.to_string(), column.to_string())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Index {
+        name: index.name,
+        columns,
+        unique: index.unique,
+        predicate: index.predicate,
+    })
 }
 
-fn parse_data_type(property_value: &Value) -> Result<RustDataType, JsonConverterError> {
+/// The key of an upsert has to be sent by the client and needs a unique index for `ON CONFLICT`. An `id` is the primary
+/// key of the api, so it gets a unique index if none was declared. Other keys must have one.
+fn parse_upsert_key<'a>(model_name: &str, parsed: &mut ParsedProperties, key: Vec<String>) -> Result<Vec<String>, JsonConverterError<'a>> {
+    let invalid = || InvalidUpsertKey(model_name.to_string(), key.join(", "));
+    let columns = key
+        .iter()
+        .map(|name| match parsed.properties.get(name) {
+            Some(property) if property.is_writable() => Ok(property.column_name(name).to_string()),
+            _ => Err(invalid()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !parsed.indexes.iter().any(|index| index.unique && index.columns == columns) {
+        if key != ["id"] {
+            return Err(invalid());
+        }
+        parsed.indexes.push(Index {
+            name: None,
+            columns: columns.clone(),
+            unique: true,
+            predicate: None,
+        });
+    }
+
+    Ok(columns)
+}
+
+/// A misspelled extension would silently change nothing, so it is printed to stderr, where a generator run shows it
+/// without a logger set up.
+fn warn_about_unknown_extensions(context: &str, object: &Map<String, Value>, known_extensions: &[&str]) {
+    for key in get_unknown_extensions(object, known_extensions) {
+        eprintln!("Ignoring unknown extension {} on {}", key, context);
+    }
+}
+
+fn get_unknown_extensions<'a>(object: &'a Map<String, Value>, known_extensions: &[&str]) -> Vec<&'a str> {
+    object
+        .keys()
+        .map(|key| key.as_str())
+        .filter(|key| key.starts_with("x-crudify-") && !known_extensions.contains(key))
+        .collect()
+}
+
+/// Security requirements protect all endpoints and their scopes become the roles, `[]` or a `{}` requirement keep the
+/// model open. `x-crudify-roles` protects the model as well and overrides the roles of all or of single endpoints.
+fn parse_access(security: Option<Vec<IndexMap<String, Vec<String>>>>, roles: Option<OA3Roles>) -> Option<Access> {
+    let scopes = match security {
+        Some(requirements) if !requirements.is_empty() && requirements.iter().all(|requirement| !requirement.is_empty()) => {
+            let mut scopes: Vec<String> = vec![];
+            for scope in requirements.into_iter().flat_map(|requirement| requirement.into_values().flatten()) {
+                if !scopes.contains(&scope) {
+                    scopes.push(scope);
+                }
+            }
+            Some(scopes)
+        }
+        _ => None,
+    };
+    let for_all = |roles: Vec<String>| Access {
+        list: roles.clone(),
+        create: roles.clone(),
+        update: roles.clone(),
+        delete: roles,
+    };
+    match roles {
+        None => scopes.map(for_all),
+        Some(OA3Roles::All(roles)) => Some(for_all(roles)),
+        Some(OA3Roles::PerEndpoint(per_endpoint)) => {
+            let scopes = scopes.unwrap_or_default();
+            Some(Access {
+                list: per_endpoint.list.unwrap_or_else(|| scopes.clone()),
+                create: per_endpoint.create.unwrap_or_else(|| scopes.clone()),
+                update: per_endpoint.update.unwrap_or_else(|| scopes.clone()),
+                delete: per_endpoint.delete.unwrap_or(scopes),
+            })
+        }
+    }
+}
+
+fn parse_oa3_type(property_value: &Value) -> Result<OA3Type, JsonConverterError<'_>> {
     let parsed_object: Result<OA3Type, serde_json::Error> = serde_json::from_value(property_value.to_owned());
     match parsed_object {
-        Err(_) => Err(AsObjectError(property_value)),
-        Ok(property_object) => {
-            let oa3_type = property_object.get_format_or_type();
-            if let Some(data_type) = DATATYPE_TO_RUST_DATATYPE.get(&oa3_type) {
-                Ok(*data_type)
-            } else {
-                Ok(RustDataType::String)
+        Ok(property_object) if property_object.kind.is_some() || property_object.reference.is_some() => Ok(property_object),
+        _ => Err(AsObjectError(property_value)),
+    }
+}
+
+fn parse_relation(property_key: &str, oa3_type: &OA3Type, required: bool) -> Option<Relation> {
+    let (target, kind) = match (oa3_type.get_reference_target(), &oa3_type.items) {
+        (Some(target), _) => (target, RelationKind::BelongsTo),
+        (None, Some(items)) if oa3_type.is_array() => (items.get_reference_target()?, RelationKind::HasMany),
+        _ => return None,
+    };
+
+    Some(Relation {
+        name: property_key.to_string(),
+        target,
+        kind,
+        required,
+        description: oa3_type.description.clone(),
+        foreign_key: None,
+    })
+}
+
+/// A relation is stored as an `{relation}_id` property on the model for a single `$ref`, and as an `{model}_id` property
+/// on the target for an array of `$ref`. The property is added with an index unless the spec already declares it.
+fn link_relations(models: &mut InternalModels) {
+    let mut foreign_keys = vec![];
+    for (model_idx, model) in models.iter().enumerate() {
+        for (relation_idx, relation) in model.relations.iter().enumerate() {
+            let target_idx = match models.iter().position(|target| target.name == relation.target) {
+                Some(target_idx) => target_idx,
+                None => {
+                    warn!("Relation {}.{} refers to unknown model {}", model.name, relation.name, relation.target);
+                    continue;
+                }
+            };
+            let (holder_idx, referenced_idx, key, required) = match relation.kind {
+                RelationKind::BelongsTo => (model_idx, target_idx, format!("{}_id", relation.name), relation.required),
+                RelationKind::HasMany => (target_idx, model_idx, format!("{}_id", model.name.to_lowercase()), false),
+            };
+            let referenced = &models[referenced_idx];
+            match referenced.properties.as_ref().and_then(|properties| properties.get("id")) {
+                Some(id) => foreign_keys.push((model_idx, relation_idx, holder_idx, key, id.data_type, required)),
+                None => warn!("Relation {}.{} is not stored, {} has no id", model.name, relation.name, referenced.name),
             }
         }
     }
+
+    for (model_idx, relation_idx, holder_idx, key, data_type, required) in foreign_keys {
+        let holder = &mut models[holder_idx];
+        let properties = holder.properties.get_or_insert_with(IndexMap::new);
+        if !properties.contains_key(&key) {
+            let mut foreign_key = Property::new(data_type);
+            foreign_key.required = required;
+            properties.insert(key.clone(), foreign_key);
+            holder.indexes.push(Index {
+                name: None,
+                columns: vec![key.clone()],
+                unique: false,
+                predicate: None,
+            });
+        }
+        models[model_idx].relations[relation_idx].foreign_key = Some(key);
+    }
+}
+
+fn parse_property(property_value: &Value, oa3_type: OA3Type, required: bool) -> Result<Property, JsonConverterError<'_>> {
+    let is_array = oa3_type.is_array();
+    let data_type = match (is_array, &oa3_type.items) {
+        (true, Some(items)) => parse_data_type(items),
+        (true, None) => return Err(AsObjectError(property_value)),
+        (false, _) => parse_data_type(&oa3_type),
+    };
+
+    let (minimum, exclusive_minimum) = get_bound(oa3_type.minimum, oa3_type.exclusive_minimum);
+    let (maximum, exclusive_maximum) = get_bound(oa3_type.maximum, oa3_type.exclusive_maximum);
+    Ok(Property {
+        data_type,
+        format: oa3_type.format.clone(),
+        is_array,
+        required,
+        nullable: oa3_type.is_nullable(),
+        default: oa3_type.default,
+        server_default: oa3_type.server_default,
+        description: oa3_type.description,
+        example: oa3_type.example,
+        enum_values: oa3_type.enum_values,
+        read_only: oa3_type.read_only,
+        write_only: oa3_type.write_only,
+        constraints: Constraints {
+            min_length: oa3_type.min_length,
+            max_length: oa3_type.max_length,
+            minimum,
+            maximum,
+            exclusive_minimum,
+            exclusive_maximum,
+            pattern: oa3_type.pattern,
+            min_items: oa3_type.min_items,
+            max_items: oa3_type.max_items,
+        },
+        storage: StorageHints {
+            column_name: oa3_type.rename,
+            sql_type: oa3_type.column_type,
+        },
+        searchable: oa3_type.searchable,
+    })
+}
+
+fn parse_data_type(oa3_type: &OA3Type) -> RustDataType {
+    oa3_type
+        .get_format_or_type()
+        .and_then(|oa3_type| DATATYPE_TO_RUST_DATATYPE.get(&oa3_type).copied())
+        .unwrap_or(RustDataType::String)
 }
 
-fn as_object(value: &Value) -> Result<&Map<String, Value>, JsonConverterError> {
+fn as_object(value: &Value) -> Result<&Map<String, Value>, JsonConverterError<'_>> {
     value.as_object().ok_or(AsObjectError(value))
 }
 
-fn as_object_with_context<'a>(value: &'a Value, ctx_value: &'a Value) -> Result<&Map<String, Value>, JsonConverterError> {
+fn as_object_with_context<'a>(value: &'a Value, ctx_value: &'a Value) -> Result<&'a Map<String, Value>, JsonConverterError<'a>> {
     value.as_object().ok_or(AsObjectError(ctx_value))
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RustDataType {
     U8,
     I32,
@@ -89,7 +583,7 @@ pub enum RustDataType {
     F32,
     F64,
     Date,
-    DateTime
+    DateTime,
 }
 
 impl Display for RustDataType {
@@ -102,8 +596,8 @@ impl Display for RustDataType {
             RustDataType::Bool => write!(f, "bool"),
             RustDataType::F32 => write!(f, "f32"),
             RustDataType::F64 => write!(f, "f64"),
-            RustDataType::Date => write!(f, "chrono::Date"),
-            RustDataType::DateTime => write!(f, "chrono::DateTime"),
+            RustDataType::Date => write!(f, "chrono::NaiveDate"),
+            RustDataType::DateTime => write!(f, "chrono::DateTime<chrono::Utc>"),
         }
     }
 }
@@ -125,6 +619,7 @@ static DATATYPE_TO_RUST_DATATYPE: phf::Map<&'static str, RustDataType> = phf_map
 };
 
 #[cfg(test)]
+#[allow(clippy::get_first)]
 mod tests {
     use super::*;
     use serde_json::json;
@@ -137,16 +632,16 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
         assert_eq!(
             "String".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("name").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("name").unwrap().data_type.to_string()
         );
         assert_eq!("OrderTwo", models.get(1).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(1).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(1).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
         assert_eq!(
             "bool".to_string(),
@@ -158,6 +653,7 @@ mod tests {
                 .unwrap()
                 .get("isFoo")
                 .unwrap()
+                .data_type
                 .to_string()
         );
     }
@@ -169,11 +665,11 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
         assert_eq!(
             "String".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("name").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("name").unwrap().data_type.to_string()
         );
     }
 
@@ -210,7 +706,7 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
     }
 
@@ -222,7 +718,7 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
     }
 
@@ -233,7 +729,7 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!(
             "i64".to_string(),
-            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().to_string()
+            models.get(0).unwrap().properties.as_ref().unwrap().get("id").unwrap().data_type.to_string()
         );
     }
 
@@ -244,4 +740,416 @@ mod tests {
         assert_eq!("Order", models.get(0).unwrap().name);
         assert_eq!("OrderTwo", models.get(1).unwrap().name);
     }
+
+    #[test]
+    fn with_required_and_metadata() {
+        let pet = json!({"Pet": {"type": "object", "required": ["name"], "description": "A pet", "properties": {
+            "name": {"type": "string", "description": "pet name", "example": "doggie", "minLength": 1, "maxLength": 64},
+            "status": {"type": "string", "default": "available", "enum": ["available", "sold"], "readOnly": true},
+            "nickname": {"type": ["string", "null"]},
+            "age": {"type": "integer", "format": "int32", "nullable": true, "minimum": 0}}}});
+        let models = convert_to_internal_model(&pet).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(Some("A pet".to_string()), model.description);
+
+        let properties = model.properties.as_ref().unwrap();
+        let name = properties.get("name").unwrap();
+        assert!(name.required);
+        assert!(!name.is_optional());
+        assert_eq!(Some(json!("doggie")), name.example);
+        assert_eq!(Some(1), name.constraints.min_length);
+        assert_eq!(Some(64), name.constraints.max_length);
+
+        let status = properties.get("status").unwrap();
+        assert!(!status.required);
+        assert!(status.read_only);
+        assert_eq!(Some(json!("available")), status.default);
+        assert_eq!(Some(vec![json!("available"), json!("sold")]), status.enum_values);
+
+        let nickname = properties.get("nickname").unwrap();
+        assert!(nickname.nullable);
+        assert_eq!(RustDataType::String, nickname.data_type);
+
+        let age = properties.get("age").unwrap();
+        assert!(age.nullable);
+        assert_eq!(RustDataType::I32, age.data_type);
+        assert_eq!(Some(0.0), age.constraints.minimum);
+    }
+
+    #[test]
+    fn with_exclusive_bounds_of_both_openapi_versions() {
+        let pet = json!({"Pet": {"type": "object", "properties": {
+            "age": {"type": "integer", "minimum": 0, "exclusiveMinimum": true, "maximum": 30},
+            "weight": {"type": "number", "exclusiveMinimum": 0.5, "exclusiveMaximum": 100}}}});
+        let models = convert_to_internal_model(&pet).unwrap();
+        let properties = models.first().unwrap().properties.as_ref().unwrap();
+
+        let age = &properties.get("age").unwrap().constraints;
+        assert_eq!((Some(0.0), true), (age.minimum, age.exclusive_minimum));
+        assert_eq!((Some(30.0), false), (age.maximum, age.exclusive_maximum));
+        let weight = &properties.get("weight").unwrap().constraints;
+        assert_eq!((Some(0.5), true), (weight.minimum, weight.exclusive_minimum));
+        assert_eq!((Some(100.0), true), (weight.maximum, weight.exclusive_maximum));
+    }
+
+    #[test]
+    fn with_array_property() {
+        let pet = json!({"Pet": {"type": "object", "properties": {"photoUrls": {"type": "array", "items": {"type": "string"}}}}});
+        let models = convert_to_internal_model(&pet).unwrap();
+        let photo_urls = models.first().unwrap().properties.as_ref().unwrap().get("photoUrls").unwrap();
+        assert!(photo_urls.is_array);
+        assert_eq!(RustDataType::String, photo_urls.data_type);
+    }
+
+    #[test]
+    fn with_array_property_without_items_must_err() {
+        let pet = json!({"Pet": {"type": "object", "properties": {"photoUrls": {"type": "array"}}}});
+        let models = convert_to_internal_model(&pet);
+        assert!(models.is_err());
+        assert_eq!(AsObjectError(&json!({"type": "array"})).to_string(), models.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn with_relations() {
+        let pet = json!({"Pet": {"type": "object", "required": ["category"], "properties": {
+            "id": {"type": "integer", "format": "int64"},
+            "category": {"$ref": "#/components/schemas/Category"},
+            "tags": {"type": "array", "items": {"$ref": "#/components/schemas/Tag"}}}}});
+        let models = convert_to_internal_model(&pet).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(vec!["id"], model.properties.as_ref().unwrap().keys().collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                Relation {
+                    name: "category".to_string(),
+                    target: "Category".to_string(),
+                    kind: RelationKind::BelongsTo,
+                    required: true,
+                    description: None,
+                    foreign_key: None
+                },
+                Relation {
+                    name: "tags".to_string(),
+                    target: "Tag".to_string(),
+                    kind: RelationKind::HasMany,
+                    required: false,
+                    description: None,
+                    foreign_key: None
+                }
+            ],
+            model.relations
+        );
+    }
+
+    #[test]
+    fn with_linked_relations() {
+        let spec = json!({
+            "Pet": {"type": "object", "required": ["category"], "properties": {
+                "id": {"type": "integer", "format": "int64"},
+                "category": {"$ref": "#/components/schemas/Category"},
+                "tags": {"type": "array", "items": {"$ref": "#/components/schemas/Tag"}}}},
+            "Category": {"type": "object", "properties": {"id": {"type": "string"}}},
+            "Tag": {"type": "object", "properties": {"name": {"type": "string"}}}});
+        let models = convert_to_internal_model(&spec).unwrap();
+        let pet = models.iter().find(|m| m.name == "Pet").unwrap();
+        let tag = models.iter().find(|m| m.name == "Tag").unwrap();
+        assert_eq!(Some("category_id".to_string()), pet.relations[0].foreign_key);
+        assert_eq!(Some("pet_id".to_string()), pet.relations[1].foreign_key);
+
+        let category_id = pet.properties.as_ref().unwrap().get("category_id").unwrap();
+        assert_eq!(RustDataType::String, category_id.data_type);
+        assert!(category_id.required);
+        assert_eq!(vec!["category_id".to_string()], pet.indexes[0].columns);
+        let pet_id = tag.properties.as_ref().unwrap().get("pet_id").unwrap();
+        assert_eq!(RustDataType::I64, pet_id.data_type);
+        assert!(!pet_id.required);
+        assert_eq!(vec!["pet_id".to_string()], tag.indexes[0].columns);
+    }
+
+    #[test]
+    fn with_relation_to_model_without_id() {
+        let spec = json!({
+            "Order": {"type": "object", "properties": {"address": {"$ref": "#/components/schemas/Address"}}},
+            "Address": {"type": "object", "properties": {"street": {"type": "string"}}}});
+        let models = convert_to_internal_model(&spec).unwrap();
+        let order = models.iter().find(|m| m.name == "Order").unwrap();
+        assert_eq!(None, order.relations[0].foreign_key);
+        assert!(order.properties.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn with_pet_example_spec() {
+        let spec: Value = serde_json::from_str(include_str!("../example_specs/pet.json")).unwrap();
+        let models = convert_to_internal_model(&spec).unwrap();
+        let pet = models.iter().find(|m| m.name == "Pet").unwrap();
+        assert!(pet.properties.as_ref().unwrap().get("name").unwrap().required);
+        assert_eq!(2, pet.relations.len());
+    }
+
+    #[test]
+    fn with_default_and_server_default() {
+        let order = json!({"Order": {"type": "object", "properties": {
+            "status": {"type": "string", "default": "placed"},
+            "shipDate": {"type": "string", "format": "date-time", "x-crudify-server-default": "now()"}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        let properties = models.first().unwrap().properties.as_ref().unwrap();
+        assert_eq!(Some(json!("placed")), properties.get("status").unwrap().default);
+        assert_eq!(Some("now()".to_string()), properties.get("shipDate").unwrap().server_default);
+        assert_eq!(RustDataType::DateTime, properties.get("shipDate").unwrap().data_type);
+    }
+
+    #[test]
+    fn with_property_that_cant_be_a_field_must_err() {
+        let order = json!({"Order": {"type": "object", "properties": {"self": {"type": "string"}}}});
+        assert_eq!(
+            "Order.self can't be a rust field, give it another column name with x-crudify-rename",
+            convert_to_internal_model(&order).unwrap_err().to_string()
+        );
+        let renamed = json!({"Order": {"type": "object", "properties": {"self": {"type": "string", "x-crudify-rename": "own"}}}});
+        assert!(convert_to_internal_model(&renamed).is_ok());
+    }
+
+    #[test]
+    fn unknown_extensions_are_reported() {
+        let order = json!({"type": "object", "x-crudify-table": "orders", "x-crudify-unknown": 1, "x-other": 2});
+        assert_eq!(vec!["x-crudify-unknown"], get_unknown_extensions(order.as_object().unwrap(), &MODEL_EXTENSIONS));
+        let kind = json!({"type": "string", "x-crudify-rename": "kind", "x-crudify-renamed": "kind"});
+        assert_eq!(vec!["x-crudify-renamed"], get_unknown_extensions(kind.as_object().unwrap(), &PROPERTY_EXTENSIONS));
+    }
+
+    #[test]
+    fn with_crudify_extensions() {
+        let order = json!({"Order": {"type": "object", "x-crudify-table": "orders", "x-crudify-skip-endpoints": ["delete"],
+            "x-crudify-soft-delete": true, "x-crudify-unknown": 1, "properties": {
+            "type": {"type": "string", "x-crudify-rename": "kind", "x-crudify-index": true},
+            "code": {"type": "string", "x-crudify-column-type": "varchar(12)", "x-crudify-unique": true}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(Some("orders".to_string()), model.options.table_name);
+        assert_eq!(vec![Endpoint::Delete], model.options.skip_endpoints);
+        assert!(model.options.soft_delete);
+        assert!(!model.has_endpoint(Endpoint::Delete));
+        assert!(model.has_endpoint(Endpoint::List));
+
+        let properties = model.properties.as_ref().unwrap();
+        assert_eq!("kind", properties.get("type").unwrap().column_name("type"));
+        assert_eq!(Some("varchar(12)".to_string()), properties.get("code").unwrap().storage.sql_type);
+        assert_eq!(
+            vec![
+                Index {
+                    name: None,
+                    columns: vec!["code".to_string()],
+                    unique: true,
+                    predicate: None
+                },
+                Index {
+                    name: None,
+                    columns: vec!["kind".to_string()],
+                    unique: false,
+                    predicate: None
+                }
+            ],
+            model.indexes
+        );
+    }
+
+    #[test]
+    fn with_unknown_skip_endpoint_must_err() {
+        let order = json!({"Order": {"type": "object", "x-crudify-skip-endpoints": ["nope"], "properties": {}}});
+        assert!(convert_to_internal_model(&order).is_err());
+    }
+
+    #[test]
+    fn with_composite_and_partial_indexes() {
+        let order = json!({"Order": {"type": "object", "x-crudify-soft-delete": true, "x-crudify-indexes": [
+            {"columns": ["petId", "status"]},
+            {"name": "order_code_key", "columns": ["code"], "unique": true, "where": "deleted_at IS NULL"},
+            {"columns": ["deleted_at"]}], "properties": {
+            "petId": {"type": "integer", "x-crudify-rename": "pet_id"},
+            "status": {"type": "string"},
+            "code": {"type": "string"}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        assert_eq!(
+            vec![
+                Index {
+                    name: None,
+                    columns: vec!["pet_id".to_string(), "status".to_string()],
+                    unique: false,
+                    predicate: None
+                },
+                Index {
+                    name: Some("order_code_key".to_string()),
+                    columns: vec!["code".to_string()],
+                    unique: true,
+                    predicate: Some("deleted_at IS NULL".to_string())
+                },
+                Index {
+                    name: None,
+                    columns: vec!["deleted_at".to_string()],
+                    unique: false,
+                    predicate: None
+                }
+            ],
+            models.first().unwrap().indexes
+        );
+    }
+
+    #[test]
+    fn with_index_on_unknown_property_must_err() {
+        let order = json!({"Order": {"type": "object", "x-crudify-indexes": [{"columns": ["nope"]}], "properties": {}}});
+        let models = convert_to_internal_model(&order);
+        assert_eq!(
+            UnknownIndexColumn("Order".to_string(), "nope".to_string()).to_string(),
+            models.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn with_versioned_model() {
+        let order = json!({"Order": {"type": "object", "x-crudify-versioned": true, "properties": {"status": {"type": "string"}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        let model = models.first().unwrap();
+        assert!(model.options.versioned);
+
+        let version = model.properties.as_ref().unwrap().get("version").unwrap();
+        assert_eq!(RustDataType::I64, version.data_type);
+        assert!(version.required && version.read_only);
+        assert_eq!(Some(json!(1)), version.default);
+    }
+
+    #[test]
+    fn with_upsert_by_id() {
+        let tag = json!({"Tag": {"type": "object", "x-crudify-upsert": true, "properties": {"id": {"type": "integer", "format": "int64"}}}});
+        let models = convert_to_internal_model(&tag).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(Some(vec!["id".to_string()]), model.options.upsert_key);
+        assert_eq!(
+            vec![Index {
+                name: None,
+                columns: vec!["id".to_string()],
+                unique: true,
+                predicate: None,
+            }],
+            model.indexes
+        );
+    }
+
+    #[test]
+    fn with_upsert_by_unique_key() {
+        let order = json!({"Order": {"type": "object", "x-crudify-upsert": ["externalId"], "properties": {
+            "externalId": {"type": "string", "x-crudify-rename": "external_id", "x-crudify-unique": true}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(Some(vec!["external_id".to_string()]), model.options.upsert_key);
+        assert_eq!(1, model.indexes.len());
+    }
+
+    #[test]
+    fn with_invalid_upsert_key_must_err() {
+        let not_unique = json!({"Order": {"type": "object", "x-crudify-upsert": ["code"], "properties": {"code": {"type": "string"}}}});
+        assert_eq!(
+            InvalidUpsertKey("Order".to_string(), "code".to_string()).to_string(),
+            convert_to_internal_model(&not_unique).unwrap_err().to_string()
+        );
+        let generated_id = json!({"Order": {"type": "object", "x-crudify-upsert": true, "properties": {
+            "id": {"type": "integer", "format": "int64", "readOnly": true}}}});
+        assert!(convert_to_internal_model(&generated_id).is_err());
+    }
+
+    #[test]
+    fn with_searchable_properties() {
+        let article = json!({"Article": {"type": "object", "x-crudify-search-config": "english", "properties": {
+            "title": {"type": "string", "x-crudify-searchable": true},
+            "views": {"type": "integer"}}}});
+        let models = convert_to_internal_model(&article).unwrap();
+        let model = models.first().unwrap();
+        assert_eq!(Some("english".to_string()), model.options.search_config);
+        let properties = model.properties.as_ref().unwrap();
+        assert!(properties.get("title").unwrap().searchable);
+        assert!(!properties.get("views").unwrap().searchable);
+
+        let article = json!({"Article": {"type": "object", "properties": {"views": {"type": "integer", "x-crudify-searchable": true}}}});
+        assert_eq!(
+            UnsearchableProperty("Article".to_string(), "views".to_string()).to_string(),
+            convert_to_internal_model(&article).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn with_security_and_roles() {
+        let order = json!({"Order": {"type": "object", "security": [{"bearer": ["clerk"]}, {"apiKey": ["clerk", "admin"]}],
+            "x-crudify-roles": {"delete": ["admin"]}, "properties": {"id": {"type": "integer"}}}});
+        let access = convert_to_internal_model(&order).unwrap()[0].options.access.clone().unwrap();
+        assert_eq!(vec!["clerk", "admin"], access.list);
+        assert_eq!(vec!["clerk", "admin"], access.update);
+        assert_eq!(vec!["admin"], access.delete);
+
+        let order = json!({"Order": {"type": "object", "security": [{"bearer": []}], "properties": {}}});
+        assert_eq!(Some(Access::default()), convert_to_internal_model(&order).unwrap()[0].options.access);
+        let order = json!({"Order": {"type": "object", "x-crudify-roles": ["admin"], "properties": {}}});
+        assert_eq!(vec!["admin"], convert_to_internal_model(&order).unwrap()[0].options.access.clone().unwrap().create);
+        for security in [json!([]), json!([{}, {"bearer": []}])] {
+            let order = json!({"Order": {"type": "object", "security": security, "properties": {}}});
+            assert_eq!(None, convert_to_internal_model(&order).unwrap()[0].options.access);
+        }
+        let order = json!({"Order": {"type": "object", "x-crudify-roles": {"read": ["admin"]}, "properties": {}}});
+        assert!(convert_to_internal_model(&order).is_err());
+    }
+
+    #[test]
+    fn with_tenancy() {
+        let order = json!({"Order": {"type": "object", "x-crudify-tenancy": "tenant", "properties": {"id": {"type": "integer"}}}});
+        let model = &convert_to_internal_model(&order).unwrap()[0];
+        assert_eq!(Some(Tenancy::Tenant), model.options.tenancy);
+        assert_eq!(Some(Access::default()), model.options.access);
+        let tenant_id = model.properties.as_ref().unwrap().get("tenant_id").unwrap();
+        assert!(tenant_id.required && tenant_id.read_only);
+        assert_eq!(Some("nullif(current_setting('crudify.tenant', true), '')".to_string()), tenant_id.server_default);
+        assert_eq!(vec!["tenant_id".to_string()], model.indexes[0].columns);
+
+        let order = json!({"Order": {"type": "object", "x-crudify-tenancy": "owner", "x-crudify-roles": ["clerk"], "properties": {}}});
+        let model = &convert_to_internal_model(&order).unwrap()[0];
+        assert!(model.properties.as_ref().unwrap().contains_key("owner_id"));
+        assert_eq!(vec!["clerk"], model.options.access.clone().unwrap().list);
+        let order = json!({"Order": {"type": "object", "x-crudify-tenancy": "team", "properties": {}}});
+        assert!(convert_to_internal_model(&order).is_err());
+    }
+
+    #[test]
+    fn with_graphql() {
+        let order = json!({"Order": {"type": "object", "x-crudify-graphql": true, "properties": {}}});
+        assert!(convert_to_internal_model(&order).unwrap()[0].options.graphql);
+        let order = json!({"Order": {"type": "object", "properties": {}}});
+        assert!(!convert_to_internal_model(&order).unwrap()[0].options.graphql);
+    }
+
+    #[test]
+    fn with_grpc() {
+        let order = json!({"Order": {"type": "object", "x-crudify-grpc": true, "properties": {}}});
+        assert!(convert_to_internal_model(&order).unwrap()[0].options.grpc);
+        let order = json!({"Order": {"type": "object", "properties": {}}});
+        assert!(!convert_to_internal_model(&order).unwrap()[0].options.grpc);
+    }
+
+    #[test]
+    fn with_timestamps_and_soft_delete() {
+        let order = json!({"Order": {"type": "object", "x-crudify-timestamps": true, "x-crudify-soft-delete": true,
+            "properties": {"status": {"type": "string"}}}});
+        let models = convert_to_internal_model(&order).unwrap();
+        let model = models.first().unwrap();
+        assert!(model.options.timestamps);
+        assert!(!model.options.history);
+
+        let properties = model.properties.as_ref().unwrap();
+        assert_eq!(
+            vec!["status", "created_at", "updated_at", "deleted_at"],
+            properties.keys().map(|key| key.as_str()).collect::<Vec<_>>()
+        );
+        let created_at = properties.get("created_at").unwrap();
+        assert!(created_at.required && created_at.read_only);
+        assert_eq!(Some("now()".to_string()), created_at.server_default);
+        let deleted_at = properties.get("deleted_at").unwrap();
+        assert_eq!(RustDataType::DateTime, deleted_at.data_type);
+        assert!(deleted_at.is_optional() && deleted_at.read_only && !deleted_at.has_default());
+    }
 }
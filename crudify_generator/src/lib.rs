@@ -2,27 +2,37 @@
 
 use std::process::Command;
 
-use indexmap::IndexMap;
-use json_converter::RustDataType;
 use serde_json::Value;
 
+mod client_creator;
 mod errors;
 mod file_creator;
+mod graphql_creator;
+mod grpc_creator;
+mod internal_model;
 mod json_converter;
 mod main_file_creator;
+mod openapi_creator;
 mod sql_creator;
+mod test_creator;
+mod ts_client_creator;
+mod web_backend;
 
-#[derive(Debug)]
-pub struct InternalModel {
-    pub name: String,
-    pub properties: Option<IndexMap<String, RustDataType>>,
-}
-
-pub type InternalModels = Vec<InternalModel>;
+pub use internal_model::{
+    Access, Constraints, Endpoint, Index, InternalModel, InternalModels, ModelOptions, Property, Relation, RelationKind, StorageHints,
+    Tenancy,
+};
+pub use json_converter::RustDataType;
+pub use web_backend::WebFramework;
 
 pub fn generate<'a>(user_id: &'a str, input_objects: &'a Value) -> Result<(), Box<dyn std::error::Error + 'a>> {
+    generate_for(user_id, input_objects, WebFramework::default())
+}
+
+/// Generates the server for the given web `framework`, `generate` writes an axum server.
+pub fn generate_for<'a>(user_id: &'a str, input_objects: &'a Value, framework: WebFramework) -> Result<(), Box<dyn std::error::Error + 'a>> {
     let models = json_converter::convert_to_internal_model(input_objects)?;
-    file_creator::write_all(user_id, &models)?;
+    file_creator::write_all(user_id, &models, framework.backend())?;
 
     Command::new("cargo")
         .current_dir(file_creator::create_or_get_project_dir(user_id)?)
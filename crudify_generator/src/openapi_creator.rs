@@ -0,0 +1,780 @@
+use serde_json::{json, Map, Value};
+
+use crate::json_converter::RustDataType;
+use crate::main_file_creator::{get_linked_relations, has_includes, has_upsert, is_omittable};
+use crate::sql_creator::is_searchable;
+use crate::{Endpoint, InternalModel, InternalModels, Property, RelationKind};
+
+/// The OpenAPI 3.1 document of the generated server. It describes the routes `create_app_fn` registers with the same
+/// request and response bodies the handlers use, so it has to follow every change to them.
+pub fn create_openapi(title: &str, models: &InternalModels) -> Value {
+    let mut paths = Map::new();
+    let mut schemas = Map::new();
+    schemas.insert("AppError".to_string(), get_error_schema());
+    schemas.insert("BulkResult".to_string(), get_bulk_result_schema());
+
+    for model in models {
+        add_model_schemas(&mut schemas, model, models);
+        add_model_paths(&mut paths, model, models);
+    }
+
+    let mut components = json!({
+        "schemas": schemas,
+        "responses": get_error_responses(),
+    });
+    if models.iter().any(|model| model.options.access.is_some()) {
+        components["securitySchemes"] = json!({
+            "bearer": {"type": "http", "scheme": "bearer", "bearerFormat": "JWT"},
+            "apiKey": {"type": "apiKey", "in": "header", "name": "X-Api-Key"},
+        });
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {"title": title, "version": "0.1.0"},
+        "paths": paths,
+        "components": components,
+    })
+}
+
+/// The body of every error response, see `AppError` in the generated server.
+fn get_error_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["status_code", "errors"],
+        "properties": {
+            "status_code": {"type": "integer", "format": "int32"},
+            "errors": {"type": "array", "items": {"type": "string"}},
+        },
+    })
+}
+
+/// One item of the `207` answer of a bulk request with `?atomic=false`, either the result or the error of the item.
+fn get_bulk_result_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["status_code"],
+        "properties": {
+            "status_code": {"type": "integer", "format": "int32"},
+            "body": {},
+            "errors": {"type": "array", "items": {"type": "string"}},
+        },
+    })
+}
+
+fn get_error_responses() -> Value {
+    let mut responses = Map::new();
+    for (name, description) in [
+        ("BadRequest", "The request is malformed or has unknown or invalid parameters"),
+        ("Unauthorized", "No valid api key or bearer token was sent"),
+        ("Forbidden", "The principal lacks the roles for the endpoint"),
+        ("NotFound", "Not found"),
+        ("Conflict", "Violates a unique or foreign key constraint"),
+        ("PreconditionFailed", "The resource was changed in the meantime"),
+        ("PreconditionRequired", "The If-Match header or the version is missing"),
+    ] {
+        responses.insert(
+            name.to_string(),
+            json!({
+                "description": description,
+                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/AppError"}}},
+            }),
+        );
+    }
+    Value::Object(responses)
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({"application/json": {"schema": schema}})
+}
+
+/// The schema of a single value of a property, as it is given in a path or a filter.
+fn get_value_schema(property: &Property) -> Value {
+    let (data_type, format) = match property.data_type {
+        RustDataType::U8 | RustDataType::I32 => ("integer", Some("int32")),
+        RustDataType::I64 => ("integer", Some("int64")),
+        RustDataType::F32 => ("number", Some("float")),
+        RustDataType::F64 => ("number", Some("double")),
+        RustDataType::String => ("string", property.format.as_deref()),
+        RustDataType::Bool => ("boolean", None),
+        RustDataType::Date => ("string", Some("date")),
+        RustDataType::DateTime => ("string", Some("date-time")),
+    };
+    let mut item = json!({ "type": data_type });
+    if let Some(format) = format {
+        item["format"] = json!(format);
+    }
+    if let Some(enum_values) = &property.enum_values {
+        item["enum"] = json!(enum_values);
+    }
+    let constraints = &property.constraints;
+    for (key, value) in [("minLength", constraints.min_length), ("maxLength", constraints.max_length)] {
+        if let Some(value) = value {
+            item[key] = json!(value);
+        }
+    }
+    if let Some(pattern) = &constraints.pattern {
+        item["pattern"] = json!(pattern);
+    }
+    // 3.1 takes the bound itself as `exclusiveMinimum`, not a flag next to `minimum`
+    for (key, exclusive_key, value, exclusive) in [
+        ("minimum", "exclusiveMinimum", constraints.minimum, constraints.exclusive_minimum),
+        ("maximum", "exclusiveMaximum", constraints.maximum, constraints.exclusive_maximum),
+    ] {
+        if let Some(value) = value {
+            item[if exclusive { exclusive_key } else { key }] = json!(value);
+        }
+    }
+
+    item
+}
+
+/// The schema of a property as the generated structs (de)serialize it, the optional ones accept `null`.
+fn get_property_schema(property: &Property) -> Value {
+    let constraints = &property.constraints;
+    let mut schema = if property.is_array {
+        let mut schema = json!({"type": "array", "items": get_value_schema(property)});
+        for (key, value) in [("minItems", constraints.min_items), ("maxItems", constraints.max_items)] {
+            if let Some(value) = value {
+                schema[key] = json!(value);
+            }
+        }
+        schema
+    } else {
+        get_value_schema(property)
+    };
+    if is_option(property) {
+        schema["type"] = json!([schema["type"], "null"]);
+        if let Some(values) = schema.get_mut("enum").and_then(Value::as_array_mut) {
+            values.push(Value::Null);
+        }
+    }
+    if let Some(description) = &property.description {
+        schema["description"] = json!(description);
+    }
+    if let Some(default) = &property.default {
+        schema["default"] = default.clone();
+    }
+    if let Some(example) = &property.example {
+        schema["examples"] = json!([example]);
+    }
+    if property.read_only {
+        schema["readOnly"] = json!(true);
+    }
+    if property.write_only {
+        schema["writeOnly"] = json!(true);
+    }
+    schema
+}
+
+/// Whether the generated struct holds the property as an `Option`.
+fn is_option(property: &Property) -> bool {
+    property.is_optional() || is_omittable(property)
+}
+
+/// An object schema of `properties`. Request bodies may leave out properties with a default.
+fn get_object_schema(model: &InternalModel, properties: &[(&String, &Property)], is_request: bool) -> Value {
+    let required: Vec<_> = properties
+        .iter()
+        .filter(|(_, property)| !(is_option(property) || is_request && property.has_default()))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties
+            .iter()
+            .map(|(key, property)| (key.to_string(), get_property_schema(property)))
+            .collect::<Map<_, _>>(),
+    });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    if let Some(description) = &model.description {
+        schema["description"] = json!(description);
+    }
+    schema
+}
+
+/// `{Model}`, `Create{Model}` and `Update{Model}` like the generated structs, and `{Model}Page` for the list endpoints.
+fn add_model_schemas(schemas: &mut Map<String, Value>, model: &InternalModel, models: &InternalModels) {
+    let properties: Vec<(&String, &Property)> = model.properties.iter().flatten().collect();
+
+    let readable: Vec<_> = properties.iter().filter(|(_, p)| p.is_readable()).copied().collect();
+    let mut schema = get_object_schema(model, &readable, false);
+    if has_includes(model, models) {
+        let includes: Map<_, _> = get_linked_relations(model, models)
+            .iter()
+            .filter(|linked| linked.has_get(model))
+            .map(|linked| {
+                let target = schema_ref(&linked.target.name);
+                let include = match linked.relation.kind {
+                    RelationKind::BelongsTo => json!({"oneOf": [target, {"type": "null"}]}),
+                    RelationKind::HasMany => json!({"type": "array", "items": target}),
+                };
+                (linked.relation.name.to_string(), include)
+            })
+            .collect();
+        schema["description"] = json!(format!(
+            "{}Related rows are embedded with `?include=`: {}.",
+            model.description.as_ref().map(|description| format!("{} ", description)).unwrap_or_default(),
+            includes.keys().cloned().collect::<Vec<_>>().join(", ")
+        ));
+        if let Some(properties) = schema["properties"].as_object_mut() {
+            properties.extend(includes);
+        }
+    }
+    schemas.insert(model.name.to_string(), schema);
+
+    let writable: Vec<_> = properties.iter().filter(|(_, p)| p.is_writable()).copied().collect();
+    if model.has_endpoint(Endpoint::Create) {
+        schemas.insert(format!("Create{}", model.name), get_object_schema(model, &writable, true));
+    }
+    if model.has_endpoint(Endpoint::Update) {
+        let updatable: Vec<_> = writable.iter().filter(|(k, _)| *k != "id").copied().collect();
+        schemas.insert(format!("Update{}", model.name), get_object_schema(model, &updatable, true));
+    }
+    if model.has_endpoint(Endpoint::List) {
+        schemas.insert(format!("{}Page", model.name), get_page_schema(schema_ref(&model.name)));
+        if is_searchable(model) {
+            let hit = json!({
+                "type": "object",
+                "required": ["item", "rank", "snippet"],
+                "properties": {
+                    "item": schema_ref(&model.name),
+                    "rank": {"type": "number", "format": "float"},
+                    "snippet": {"type": "string"},
+                },
+            });
+            schemas.insert(format!("{}SearchPage", model.name), get_page_schema(hit));
+        }
+        if model.options.history {
+            schemas.insert(format!("{}HistoryEntry", model.name), get_history_entry_schema());
+        }
+    }
+}
+
+fn get_page_schema(item: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": ["items", "total"],
+        "properties": {
+            "items": {"type": "array", "items": item},
+            "total": {"type": "integer", "format": "int64"},
+            "next_cursor": {"type": ["string", "null"], "description": "Pass as `cursor` to get the next page"},
+        },
+    })
+}
+
+/// The rows are the whole table rows by column, with the write only properties.
+fn get_history_entry_schema() -> Value {
+    let row = json!({"type": ["object", "null"]});
+    json!({
+        "type": "object",
+        "required": ["history_id", "operation", "changed_by", "changed_at"],
+        "properties": {
+            "history_id": {"type": "integer", "format": "int64"},
+            "operation": {"type": "string", "enum": ["INSERT", "UPDATE", "DELETE"]},
+            "changed_by": {"type": "string"},
+            "changed_at": {"type": "string", "format": "date-time"},
+            "old_row": row,
+            "new_row": row,
+        },
+    })
+}
+
+/// The schema of the `:id` path parameter, `int64` for models without an `id` like `get_id_type`.
+fn get_id_schema(model: &InternalModel) -> Value {
+    match model.properties.as_ref().and_then(|properties| properties.get("id")) {
+        Some(id) => get_value_schema(id),
+        None => json!({"type": "integer", "format": "int64"}),
+    }
+}
+
+fn query_param(name: &str, schema: Value, description: &str) -> Value {
+    json!({"name": name, "in": "query", "required": false, "schema": schema, "description": description})
+}
+
+/// `limit`, `offset`, `cursor`, `sort` and the filters of every readable, non array property, see `list_query`.
+fn get_list_params(model: &InternalModel, models: &InternalModels) -> Vec<Value> {
+    let mut params = vec![
+        query_param("limit", json!({"type": "integer", "minimum": 1, "maximum": 1000, "default": 100}), "Rows per page"),
+        query_param("offset", json!({"type": "integer", "minimum": 0}), "Rows to skip, can't be combined with `cursor`"),
+        query_param("cursor", json!({"type": "string"}), "The `next_cursor` of the previous page"),
+        query_param("sort", json!({"type": "string"}), "Comma separated fields, `-field` sorts descending"),
+    ];
+    if model.options.soft_delete {
+        params.push(query_param("include_deleted", json!({"type": "boolean"}), "Lists soft deleted rows as well"));
+    }
+    if has_includes(model, models) {
+        params.push(get_include_param(model, models));
+    }
+    for (key, property) in model.properties.iter().flatten() {
+        if !property.is_readable() || property.is_array {
+            continue;
+        }
+        let schema = get_value_schema(property);
+        params.push(query_param(key, schema.clone(), &format!("{} equals", key)));
+        params.push(query_param(&format!("{}[lt]", key), schema.clone(), &format!("{} is less than", key)));
+        params.push(query_param(&format!("{}[gt]", key), schema, &format!("{} is greater than", key)));
+        params.push(query_param(&format!("{}[in]", key), json!({"type": "string"}), &format!("{} is one of the comma separated values", key)));
+        if property.data_type == RustDataType::String {
+            params.push(query_param(&format!("{}[like]", key), json!({"type": "string"}), &format!("{} matches the LIKE pattern", key)));
+        }
+    }
+    params
+}
+
+fn get_include_param(model: &InternalModel, models: &InternalModels) -> Value {
+    let relations: Vec<_> = get_linked_relations(model, models)
+        .iter()
+        .filter(|linked| linked.has_get(model))
+        .map(|linked| linked.relation.name.to_string())
+        .collect();
+    query_param(
+        "include",
+        json!({"type": "string"}),
+        &format!("Comma separated relations to embed: {}", relations.join(", ")),
+    )
+}
+
+fn get_atomic_param() -> Value {
+    query_param(
+        "atomic",
+        json!({"type": "boolean", "default": true}),
+        "With `false` every item succeeds or fails on its own and the results are sent with `207`",
+    )
+}
+
+fn get_if_match_param() -> Value {
+    json!({
+        "name": "If-Match",
+        "in": "header",
+        "required": true,
+        "schema": {"type": "string"},
+        "description": "The `ETag` of the version to change, `*` for any",
+    })
+}
+
+/// A row response, with its `ETag` for a versioned model.
+fn get_row_response(model: &InternalModel, description: &str, schema: Value) -> Value {
+    let mut response = json!({"description": description, "content": json_content(schema)});
+    if model.options.versioned {
+        response["headers"] = json!({"ETag": {"schema": {"type": "string"}, "description": "The version of the row"}});
+    }
+    response
+}
+
+fn get_created_response(model: &InternalModel, schema: Value) -> Value {
+    let mut response = get_row_response(model, "Created", schema);
+    response["headers"]["Location"] = json!({"schema": {"type": "string"}, "description": "The path of the created row"});
+    response
+}
+
+fn get_bulk_responses(status: &str, response: Value) -> Value {
+    json!({
+        status: response,
+        "207": {
+            "description": "The result of every item, for `?atomic=false`",
+            "content": json_content(json!({"type": "array", "items": schema_ref("BulkResult")})),
+        },
+    })
+}
+
+/// An operation on `model` and the endpoints it needs, the error responses follow from the model options.
+struct Operation<'a> {
+    id: String,
+    summary: String,
+    required: Vec<(&'a InternalModel, Endpoint)>,
+    params: Vec<Value>,
+    body: Option<Value>,
+    responses: Value,
+}
+
+impl Operation<'_> {
+    fn into_value(self, tag: &str) -> Value {
+        let mut operation = json!({
+            "operationId": self.id,
+            "summary": self.summary,
+            "tags": [tag],
+        });
+        if !self.params.is_empty() {
+            operation["parameters"] = json!(self.params);
+        }
+        if let Some(body) = self.body {
+            operation["requestBody"] = json!({"required": true, "content": json_content(body)});
+        }
+        let mut responses = self.responses;
+        responses["400"] = json!({"$ref": "#/components/responses/BadRequest"});
+        if self.required.iter().any(|(model, _)| model.options.access.is_some()) {
+            responses["401"] = json!({"$ref": "#/components/responses/Unauthorized"});
+            responses["403"] = json!({"$ref": "#/components/responses/Forbidden"});
+            operation["security"] = json!(get_security(&self.required));
+        }
+        operation["responses"] = responses;
+        operation
+    }
+}
+
+/// Either credential works. The roles one of which is needed for every required endpoint are listed with the schemes.
+fn get_security(required: &[(&InternalModel, Endpoint)]) -> Value {
+    let mut roles: Vec<String> = vec![];
+    for (model, endpoint) in required {
+        if let Some(access) = &model.options.access {
+            roles.extend(access.roles(*endpoint).iter().cloned());
+        }
+    }
+    roles.sort();
+    roles.dedup();
+    json!([{"bearer": roles}, {"apiKey": roles}])
+}
+
+fn add_operation(paths: &mut Map<String, Value>, path: &str, method: &str, operation: Operation, tag: &str) {
+    let item = paths.entry(path.to_string()).or_insert_with(|| json!({}));
+    item[method] = operation.into_value(tag);
+}
+
+fn add_model_paths(paths: &mut Map<String, Value>, model: &InternalModel, models: &InternalModels) {
+    let name = model.name.to_lowercase();
+    let collection = format!("/api/{}", name);
+    let row = format!("/api/{}/{{id}}", name);
+    let bulk = format!("/api/{}/bulk", name);
+    let id_param = json!({"name": "id", "in": "path", "required": true, "schema": get_id_schema(model)});
+    let versioned = model.options.versioned;
+    let not_found = json!({"$ref": "#/components/responses/NotFound"});
+    let conflict = json!({"$ref": "#/components/responses/Conflict"});
+    let with_versioning = |mut responses: Value| {
+        if versioned {
+            responses["412"] = json!({"$ref": "#/components/responses/PreconditionFailed"});
+            responses["428"] = json!({"$ref": "#/components/responses/PreconditionRequired"});
+        }
+        responses
+    };
+    let with_if_match = |mut params: Vec<Value>| {
+        if versioned {
+            params.push(get_if_match_param());
+        }
+        params
+    };
+
+    if model.has_endpoint(Endpoint::List) {
+        add_operation(paths, &collection, "get", Operation {
+            id: format!("get_{}", name),
+            summary: format!("List {} rows", model.name),
+            required: vec![(model, Endpoint::List)],
+            params: get_list_params(model, models),
+            body: None,
+            responses: json!({"200": {"description": "A page of rows", "content": json_content(schema_ref(&format!("{}Page", model.name)))}}),
+        }, &model.name);
+
+        let mut params = vec![id_param.clone()];
+        if model.options.soft_delete {
+            params.push(query_param("include_deleted", json!({"type": "boolean"}), "Finds a soft deleted row as well"));
+        }
+        if has_includes(model, models) {
+            params.push(get_include_param(model, models));
+        }
+        add_operation(paths, &row, "get", Operation {
+            id: format!("get_{}_by_id", name),
+            summary: format!("Get a {} by id", model.name),
+            required: vec![(model, Endpoint::List)],
+            params,
+            body: None,
+            responses: json!({"200": get_row_response(model, "The row", schema_ref(&model.name)), "404": not_found}),
+        }, &model.name);
+
+        if is_searchable(model) {
+            add_operation(paths, &format!("/api/{}/search", name), "get", Operation {
+                id: format!("search_{}", name),
+                summary: format!("Full text search over {} rows", model.name),
+                required: vec![(model, Endpoint::List)],
+                params: vec![
+                    json!({"name": "q", "in": "query", "required": true, "schema": {"type": "string"}, "description": "The search terms"}),
+                    query_param("limit", json!({"type": "integer", "minimum": 1, "maximum": 1000, "default": 100}), "Hits per page"),
+                    query_param("offset", json!({"type": "integer", "minimum": 0}), "Hits to skip"),
+                ],
+                body: None,
+                responses: json!({"200": {"description": "The best ranked hits", "content": json_content(schema_ref(&format!("{}SearchPage", model.name)))}}),
+            }, &model.name);
+        }
+        if model.options.history {
+            add_operation(paths, &format!("{}/history", row), "get", Operation {
+                id: format!("get_{}_history", name),
+                summary: format!("Every change of a {}", model.name),
+                required: vec![(model, Endpoint::List)],
+                params: vec![id_param.clone()],
+                body: None,
+                responses: json!({"200": {
+                    "description": "The changes, oldest first",
+                    "content": json_content(json!({"type": "array", "items": schema_ref(&format!("{}HistoryEntry", model.name))})),
+                }}),
+            }, &model.name);
+        }
+    }
+
+    if model.has_endpoint(Endpoint::Create) {
+        add_operation(paths, &collection, "post", Operation {
+            id: format!("post_{}", name),
+            summary: format!("Create a {}", model.name),
+            required: vec![(model, Endpoint::Create)],
+            params: vec![],
+            body: Some(schema_ref(&format!("Create{}", model.name))),
+            responses: json!({"201": get_created_response(model, schema_ref(&model.name)), "409": conflict}),
+        }, &model.name);
+        add_operation(paths, &bulk, "post", Operation {
+            id: format!("post_{}_bulk", name),
+            summary: format!("Create many {} rows", model.name),
+            required: vec![(model, Endpoint::Create)],
+            params: vec![get_atomic_param()],
+            body: Some(json!({"type": "array", "items": schema_ref(&format!("Create{}", model.name))})),
+            responses: get_bulk_responses("201", json!({
+                "description": "Created",
+                "content": json_content(json!({"type": "array", "items": schema_ref(&model.name)})),
+            })),
+        }, &model.name);
+    }
+    if has_upsert(model) {
+        add_operation(paths, &collection, "put", Operation {
+            id: format!("put_{}_upsert", name),
+            summary: format!("Create a {} or update the one with the same {}", model.name, model.options.upsert_key.iter().flatten().cloned().collect::<Vec<_>>().join(", ")),
+            required: vec![(model, Endpoint::Create), (model, Endpoint::Update)],
+            params: vec![],
+            body: Some(schema_ref(&format!("Create{}", model.name))),
+            responses: json!({
+                "200": get_row_response(model, "Updated", schema_ref(&model.name)),
+                "201": get_created_response(model, schema_ref(&model.name)),
+                "409": conflict,
+            }),
+        }, &model.name);
+    }
+
+    if model.has_endpoint(Endpoint::Update) {
+        add_operation(paths, &row, "put", Operation {
+            id: format!("put_{}", name),
+            summary: format!("Replace a {}", model.name),
+            required: vec![(model, Endpoint::Update)],
+            params: with_if_match(vec![id_param.clone()]),
+            body: Some(schema_ref(&format!("Update{}", model.name))),
+            responses: with_versioning(json!({"200": get_row_response(model, "Updated", schema_ref(&model.name)), "404": not_found, "409": conflict})),
+        }, &model.name);
+        add_operation(paths, &row, "patch", Operation {
+            id: format!("patch_{}", name),
+            summary: format!("Change some properties of a {}", model.name),
+            required: vec![(model, Endpoint::Update)],
+            params: with_if_match(vec![id_param.clone()]),
+            body: Some(json!({"type": "object", "description": format!("Any properties of Update{}", model.name)})),
+            responses: with_versioning(json!({"200": get_row_response(model, "Updated", schema_ref(&model.name)), "404": not_found, "409": conflict})),
+        }, &model.name);
+        let mut item = json!({"type": "object", "required": ["id"], "properties": {"id": get_id_schema(model)}});
+        if versioned {
+            item["required"] = json!(["id", "version"]);
+            item["properties"]["version"] = json!({"type": "integer", "format": "int64"});
+        }
+        item["description"] = json!(format!("The id and any properties of Update{}", model.name));
+        add_operation(paths, &bulk, "patch", Operation {
+            id: format!("patch_{}_bulk", name),
+            summary: format!("Change many {} rows", model.name),
+            required: vec![(model, Endpoint::Update)],
+            params: vec![get_atomic_param()],
+            body: Some(json!({"type": "array", "items": item})),
+            responses: with_versioning(get_bulk_responses("200", json!({
+                "description": "Updated",
+                "content": json_content(json!({"type": "array", "items": schema_ref(&model.name)})),
+            }))),
+        }, &model.name);
+    }
+
+    if model.has_endpoint(Endpoint::Delete) {
+        add_operation(paths, &row, "delete", Operation {
+            id: format!("delete_{}", name),
+            summary: format!("Delete a {}", model.name),
+            required: vec![(model, Endpoint::Delete)],
+            params: with_if_match(vec![id_param.clone()]),
+            body: None,
+            responses: with_versioning(json!({"204": {"description": "Deleted"}, "404": not_found})),
+        }, &model.name);
+        let item = if versioned {
+            json!({
+                "type": "object",
+                "required": ["id", "version"],
+                "properties": {"id": get_id_schema(model), "version": {"type": "integer", "format": "int64"}},
+            })
+        } else {
+            get_id_schema(model)
+        };
+        add_operation(paths, &bulk, "delete", Operation {
+            id: format!("delete_{}_bulk", name),
+            summary: format!("Delete many {} rows", model.name),
+            required: vec![(model, Endpoint::Delete)],
+            params: vec![get_atomic_param()],
+            body: Some(json!({"type": "array", "items": item})),
+            responses: with_versioning(get_bulk_responses("204", json!({"description": "Deleted"}))),
+        }, &model.name);
+    }
+
+    for linked in get_linked_relations(model, models) {
+        let path = format!("{}/{}", row, linked.relation.name);
+        let target = &linked.target.name;
+        if linked.has_get(model) {
+            let (params, response) = match linked.relation.kind {
+                RelationKind::BelongsTo => (
+                    vec![id_param.clone()],
+                    json!({"description": format!("The {}", linked.relation.name), "content": json_content(schema_ref(target))}),
+                ),
+                RelationKind::HasMany => {
+                    let mut params = vec![id_param.clone()];
+                    params.extend(get_list_params(linked.target, models));
+                    (params, json!({"description": "A page of rows", "content": json_content(schema_ref(&format!("{}Page", target)))}))
+                }
+            };
+            add_operation(paths, &path, "get", Operation {
+                id: format!("get_{}_{}", name, linked.relation.name.to_lowercase()),
+                summary: format!("Get the {} of a {}", linked.relation.name, model.name),
+                required: vec![(model, Endpoint::List), (linked.target, Endpoint::List)],
+                params,
+                body: None,
+                responses: json!({"200": response, "404": not_found}),
+            }, &model.name);
+        }
+        if linked.has_post(model) {
+            let required = match linked.relation.kind {
+                RelationKind::BelongsTo => vec![(linked.target, Endpoint::Create), (model, Endpoint::Update)],
+                RelationKind::HasMany => vec![(linked.target, Endpoint::Create), (model, Endpoint::List)],
+            };
+            add_operation(paths, &path, "post", Operation {
+                id: format!("post_{}_{}", name, linked.relation.name.to_lowercase()),
+                summary: format!("Create a {} as {} of a {}", target, linked.relation.name, model.name),
+                required,
+                params: vec![id_param.clone()],
+                body: Some(schema_ref(&format!("Create{}", target))),
+                responses: json!({"201": get_created_response(linked.target, schema_ref(target)), "404": not_found, "409": conflict}),
+            }, &model.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_converter::convert_to_internal_model;
+
+    use super::*;
+
+    fn pet_store() -> InternalModels {
+        let spec = json!({
+            "Category": {"type": "object", "properties": {"id": {"type": "integer", "format": "int64", "readOnly": true}, "name": {"type": "string"}}},
+            "Pet": {
+                "type": "object",
+                "required": ["name"],
+                "x-crudify-versioned": true,
+                "x-crudify-soft-delete": true,
+                "properties": {
+                    "id": {"type": "integer", "format": "int64", "readOnly": true},
+                    "name": {"type": "string", "maxLength": 20, "example": "doggie"},
+                    "status": {"type": "string", "enum": ["available", "sold"], "default": "available"},
+                    "secret": {"type": "string", "format": "password"},
+                    "category": {"$ref": "#/components/schemas/Category"}
+                }
+            }
+        });
+        convert_to_internal_model(&spec).unwrap()
+    }
+
+    #[test]
+    fn test_model_schemas() {
+        let openapi = create_openapi("pets", &pet_store());
+        assert_eq!("3.1.0", openapi["openapi"]);
+        let schemas = &openapi["components"]["schemas"];
+
+        let pet = &schemas["Pet"];
+        assert_eq!(json!({"type": ["integer", "null"], "format": "int64", "readOnly": true}), pet["properties"]["id"]);
+        assert_eq!(json!({"type": "string", "maxLength": 20, "examples": ["doggie"]}), pet["properties"]["name"]);
+        assert!(pet["properties"].get("secret").is_none());
+        assert_eq!(json!({"$ref": "#/components/schemas/Category"}), pet["properties"]["category"]["oneOf"][0]);
+        assert!(pet["required"].as_array().unwrap().contains(&json!("name")));
+
+        let create = &schemas["CreatePet"];
+        assert_eq!(json!(["name"]), create["required"]);
+        assert_eq!(json!(["available", "sold", null]), create["properties"]["status"]["enum"]);
+        assert_eq!(json!("available"), create["properties"]["status"]["default"]);
+        assert_eq!(json!("password"), create["properties"]["secret"]["format"]);
+        assert!(create["properties"].get("id").is_none());
+        assert!(schemas["UpdatePet"]["properties"].get("version").is_none());
+        assert_eq!(json!({"$ref": "#/components/schemas/Pet"}), schemas["PetPage"]["properties"]["items"]["items"]);
+        assert_eq!(json!(["status_code", "errors"]), schemas["AppError"]["required"]);
+    }
+
+    #[test]
+    fn test_exclusive_bounds_are_read_back() {
+        let spec = json!({"Pet": {"type": "object", "properties": {
+            "weight": {"type": "number", "minimum": 0, "exclusiveMinimum": true, "maximum": 100}}}});
+        let openapi = create_openapi("pets", &convert_to_internal_model(&spec).unwrap());
+        let weight = &openapi["components"]["schemas"]["Pet"]["properties"]["weight"];
+        assert_eq!(json!(0.0), weight["exclusiveMinimum"]);
+
+        let models = convert_to_internal_model(&json!({"Pet": openapi["components"]["schemas"]["Pet"]})).unwrap();
+        let constraints = &models[0].properties.as_ref().unwrap()["weight"].constraints;
+        assert_eq!((Some(0.0), true, Some(100.0), false), (
+            constraints.minimum,
+            constraints.exclusive_minimum,
+            constraints.maximum,
+            constraints.exclusive_maximum
+        ));
+    }
+
+    #[test]
+    fn test_paths_follow_the_generated_routes() {
+        let mut models = pet_store();
+        models[0].options.skip_endpoints = vec![Endpoint::Delete];
+        let openapi = create_openapi("pets", &models);
+        let paths = &openapi["paths"];
+
+        assert!(paths["/api/category/{id}"].get("delete").is_none());
+        assert!(paths["/api/category/bulk"].get("delete").is_none());
+        assert_eq!("get_category_by_id", paths["/api/category/{id}"]["get"]["operationId"]);
+
+        let list = &paths["/api/pet"]["get"];
+        let params: Vec<_> = list["parameters"].as_array().unwrap().iter().map(|param| param["name"].as_str().unwrap()).collect();
+        for param in ["limit", "offset", "cursor", "sort", "include_deleted", "include", "name", "name[lt]", "name[in]", "name[like]"] {
+            assert!(params.contains(&param), "{}", param);
+        }
+        assert!(!params.contains(&"secret") && !params.contains(&"id[like]"));
+
+        let put = &paths["/api/pet/{id}"]["put"];
+        assert_eq!("If-Match", put["parameters"][1]["name"]);
+        assert_eq!(json!({"$ref": "#/components/responses/PreconditionFailed"}), put["responses"]["412"]);
+        assert!(put["responses"]["200"]["headers"].get("ETag").is_some());
+        assert!(put.get("security").is_none());
+        assert!(paths["/api/pet"]["post"]["responses"]["201"]["headers"].get("Location").is_some());
+        assert_eq!(json!(["id", "version"]), paths["/api/pet/bulk"]["delete"]["requestBody"]["content"]["application/json"]["schema"]["items"]["required"]);
+        assert!(paths["/api/pet/bulk"]["post"]["responses"].get("207").is_some());
+        assert_eq!(
+            json!({"$ref": "#/components/schemas/Category"}),
+            paths["/api/pet/{id}/category"]["get"]["responses"]["200"]["content"]["application/json"]["schema"]
+        );
+        assert!(paths["/api/pet"].get("put").is_none());
+        assert!(paths.get("/api/pet/search").is_none());
+    }
+
+    #[test]
+    fn test_protected_operations() {
+        let mut models = pet_store();
+        models[1].options.access = Some(crate::Access {
+            delete: vec!["admin".to_string()],
+            ..Default::default()
+        });
+        let openapi = create_openapi("pets", &models);
+
+        assert_eq!("X-Api-Key", openapi["components"]["securitySchemes"]["apiKey"]["name"]);
+        let delete = &openapi["paths"]["/api/pet/{id}"]["delete"];
+        assert_eq!(json!([{"bearer": ["admin"]}, {"apiKey": ["admin"]}]), delete["security"]);
+        assert_eq!(json!({"$ref": "#/components/responses/Forbidden"}), delete["responses"]["403"]);
+        assert_eq!(json!([{"bearer": []}, {"apiKey": []}]), openapi["paths"]["/api/pet"]["get"]["security"]);
+        assert!(openapi["paths"]["/api/category"]["get"].get("security").is_none());
+        assert!(create_openapi("pets", &pet_store())["components"].get("securitySchemes").is_none());
+    }
+}
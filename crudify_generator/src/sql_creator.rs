@@ -1,149 +1,1367 @@
-use indexmap::IndexMap;
-use phf::phf_map;
+use serde_json::Value;
 
-use crate::{json_converter::RustDataType, InternalModel, errors::SqlConverterError};
+use crate::{json_converter::RustDataType, Index, InternalModel, InternalModels, Property};
 
-pub fn create_get_all_entities(model: &InternalModel) -> String {
-    let mut sql: String = "SELECT ".to_string();
+/// The schema and the table, quoted as a model may be named like a keyword of SQLite, i.e. `public."order"`.
+pub fn get_table_name(model: &InternalModel) -> String {
+    format!("{}.{}", get_schema_name(model), quote_identifier(&get_unqualified_table_name(model)))
+}
+
+/// The table name without its schema, used to name indexes.
+fn get_unqualified_table_name(model: &InternalModel) -> String {
+    match &model.options.table_name {
+        Some(table_name) => table_name.rsplit('.').next().unwrap_or(table_name).to_string(),
+        None => model.name.to_lowercase(),
+    }
+}
+
+/// The schema of the table, `public` unless the table name has another.
+pub fn get_schema_name(model: &InternalModel) -> String {
+    match &model.options.table_name {
+        Some(table_name) => table_name.rsplit_once('.').map(|(schema, _)| schema).unwrap_or("public").to_string(),
+        None => "public".to_string(),
+    }
+}
+
+/// Postgres folds unquoted names to lower case, so column names are quoted to keep the case of e.g. `petId`.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+pub fn get_id_column(model: &InternalModel) -> String {
+    let column = model
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get("id"))
+        .map(|id| id.column_name("id"))
+        .unwrap_or("id");
+    quote_identifier(column)
+}
 
-    let fields: String = match &model.properties {
+/// Soft deleted rows and the rows of other tenants or owners are hidden from every read and write.
+fn get_visible_condition(model: &InternalModel) -> String {
+    let not_deleted = if model.options.soft_delete { " AND deleted_at IS NULL" } else { "" };
+    format!("{}{}", get_tenancy_condition(model), not_deleted)
+}
+
+fn get_tenancy_condition(model: &InternalModel) -> String {
+    match get_tenancy_filter(model) {
+        Some(filter) => format!(" AND {}", filter),
+        None => "".to_string(),
+    }
+}
+
+/// The condition limiting a tenant scoped model to the rows of the tenant or owner the transaction is set to.
+pub fn get_tenancy_filter(model: &InternalModel) -> Option<String> {
+    model
+        .options
+        .tenancy
+        .map(|tenancy| format!("{} = {}", quote_identifier(tenancy.column()), tenancy.current_value()))
+}
+
+/// The columns sent back to clients, `writeOnly` and `password` columns are never selected.
+pub fn get_select_columns(model: &InternalModel) -> String {
+    match &model.properties {
         None => "*".to_string(),
-        Some(properties) => properties.keys().map(|p| p.as_ref()).collect::<Vec<_>>().join(", "),
-    };
+        Some(properties) => properties
+            .iter()
+            .filter(|(_, property)| property.is_readable())
+            .map(|(key, property)| quote_identifier(property.column_name(key)))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// The unfiltered list query, `list_query` in the generated project appends the conditions, sorting and paging.
+pub fn create_get_all_entities(model: &InternalModel) -> String {
+    let mut sql: String = "SELECT ".to_string();
 
-    sql.push_str(&fields);
-    sql.push_str(&format!(" FROM public.{}", &model.name.to_lowercase()));
+    sql.push_str(&get_select_columns(model));
+    sql.push_str(&format!(" FROM {}", get_table_name(model)));
 
     sql
 }
 
+pub fn create_count_entities(model: &InternalModel) -> String {
+    format!("SELECT count(*) FROM {}", get_table_name(model))
+}
+
+/// The condition hiding soft deleted rows from a list, unless the request asks for them.
+pub fn get_not_deleted_filter(model: &InternalModel) -> Option<&'static str> {
+    if model.options.soft_delete {
+        Some("deleted_at IS NULL")
+    } else {
+        None
+    }
+}
+
 pub fn create_create_entity(model: &InternalModel) -> String {
     let mut sql: String = "INSERT INTO ".to_string();
-    sql.push_str(&format!("public.{} ", &model.name.to_lowercase()));
+    sql.push_str(&format!("{} ", get_table_name(model)));
 
-    let fields = format!(
-        "({})",
-        model
-            .properties
-            .as_ref()
-            .map(|properties| properties.keys().map(|p| p.as_ref()).collect::<Vec<_>>().join(", "))
-            .unwrap_or_default()
-    );
+    let columns: Vec<String> = match &model.properties {
+        None => vec![],
+        Some(properties) => properties
+            .iter()
+            .filter(|(_, property)| property.is_writable())
+            .map(|(key, property)| quote_identifier(property.column_name(key)))
+            .collect(),
+    };
+
+    if columns.is_empty() {
+        sql.push_str("DEFAULT VALUES");
+    } else {
+        sql.push_str(&format!("({})", columns.join(", ")));
+        sql.push_str(" VALUES ");
+        sql.push_str(&format!(
+            "({})",
+            (1..columns.len() + 1).map(|idx| format!("${}", idx)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    sql.push_str(&format!(" RETURNING {}", get_select_columns(model)));
+
+    sql
+}
+
+/// An INSERT that updates the row with the same upsert key instead of failing, the writable properties are bound in
+/// order. Besides the row it returns whether it was `inserted`.
+pub fn create_upsert_entity(model: &InternalModel) -> String {
+    let columns: Vec<String> = match &model.properties {
+        None => vec![],
+        Some(properties) => properties
+            .iter()
+            .filter(|(_, property)| property.is_writable())
+            .map(|(key, property)| quote_identifier(property.column_name(key)))
+            .collect(),
+    };
+    let values = (1..columns.len() + 1).map(|idx| format!("${}", idx)).collect::<Vec<_>>();
+    let mut assignments: Vec<String> = columns
+        .iter()
+        .filter(|column| !get_upsert_key(model).contains(column))
+        .map(|column| format!("{0} = EXCLUDED.{0}", column))
+        .collect();
+    assignments.extend(get_upsert_assignments(model).iter().map(|assignment| assignment.to_string()));
+    if assignments.is_empty() {
+        assignments = get_upsert_key(model).iter().map(|column| format!("{0} = EXCLUDED.{0}", column)).collect();
+    }
 
-    sql.push_str(&fields);
-    sql.push_str(" VALUES ");
+    format!(
+        "INSERT INTO {} AS existing ({}) VALUES ({}) ON CONFLICT {} DO UPDATE SET {}{} RETURNING {}, xmax = 0 AS inserted",
+        get_table_name(model),
+        columns.join(", "),
+        values.join(", "),
+        get_upsert_conflict(model),
+        assignments.join(", "),
+        get_upsert_condition(model),
+        get_select_columns(model)
+    )
+}
 
-    let values: String = match &model.properties {
+/// A row of another tenant or owner with the same key is left alone, the upsert then returns no row.
+pub fn get_upsert_condition(model: &InternalModel) -> String {
+    match model.options.tenancy {
+        Some(tenancy) => format!(" WHERE existing.{0} = EXCLUDED.{0}", quote_identifier(tenancy.column())),
         None => "".to_string(),
+    }
+}
+
+pub fn get_upsert_key(model: &InternalModel) -> Vec<String> {
+    match &model.options.upsert_key {
+        None => vec![],
+        Some(key) => key.iter().map(|column| quote_identifier(column)).collect(),
+    }
+}
+
+/// The `ON CONFLICT` target, with the condition of the unique index if it is a partial one.
+pub fn get_upsert_conflict(model: &InternalModel) -> String {
+    let predicate = model
+        .indexes
+        .iter()
+        .find(|index| index.unique && Some(&index.columns) == model.options.upsert_key.as_ref())
+        .and_then(|index| index.predicate.as_ref());
+    let key = get_upsert_key(model).join(", ");
+    match predicate {
+        Some(predicate) => format!("({}) WHERE {}", key, predicate),
+        None => format!("({})", key),
+    }
+}
+
+/// Columns the server maintains when an upsert updates a row, the existing row is aliased as `existing`. A soft deleted
+/// row is brought back. `CURRENT_TIMESTAMP` is the `now()` of Postgres that SQLite understands as well.
+pub fn get_upsert_assignments(model: &InternalModel) -> Vec<&'static str> {
+    let mut assignments = vec![];
+    if model.options.versioned {
+        assignments.push("version = existing.version + 1");
+    }
+    if model.options.timestamps {
+        assignments.push("updated_at = CURRENT_TIMESTAMP");
+    }
+    if model.options.soft_delete {
+        assignments.push("deleted_at = NULL");
+    }
+
+    assignments
+}
+
+/// `$1` is the id from the request path, `$2` the expected version of a versioned model. The writable properties of the
+/// body follow in order.
+pub fn create_update_entity(model: &InternalModel) -> String {
+    let mut fields: Vec<String> = match &model.properties {
+        None => vec![],
         Some(properties) => {
-            format!(
-                "({})",
-                (1..properties.keys().len() + 1)
-                    .map(|idx| format!("${}", idx))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )
+            let mut idx = get_update_condition_params(model);
+            properties
+                .iter()
+                .filter(|(_, property)| property.is_writable())
+                .map(|(k, property)| {
+                    if k == "id" {
+                        format!("{} = $1", quote_identifier(property.column_name(k)))
+                    } else {
+                        idx += 1;
+                        format!("{} = ${}", quote_identifier(property.column_name(k)), idx)
+                    }
+                })
+                .collect()
         }
     };
+    fields.extend(get_update_assignments(model).iter().map(|assignment| assignment.to_string()));
 
-    sql.push_str(&values);
+    format!(
+        "UPDATE {} SET {} WHERE {} RETURNING {}",
+        get_table_name(model),
+        fields.join(", "),
+        get_update_condition(model),
+        get_select_columns(model)
+    )
+}
 
-    sql
+/// The row with the id in `$1`.
+pub fn get_id_condition(model: &InternalModel) -> String {
+    format!("{} = $1{}", get_id_column(model), get_visible_condition(model))
 }
 
-pub fn create_update_entity(model: &InternalModel) -> String {
-    let fields: String = match &model.properties {
-        None => "".to_string(),
-        Some(properties) => properties
-            .keys()
-            .enumerate()
-            .into_iter()
-            .map(|(idx, k)| format!("{} = ${}", k, idx + 1))
-            .collect::<Vec<_>>()
-            .join(", "),
+/// The row an update or delete with the id in `$1` applies to. A versioned row has to still be at the version in `$2`,
+/// `NULL` skips the check for an `If-Match: *`.
+pub fn get_update_condition(model: &InternalModel) -> String {
+    if model.options.versioned {
+        format!(
+            "{} = $1 AND version = COALESCE($2, version){}",
+            get_id_column(model),
+            get_visible_condition(model)
+        )
+    } else {
+        get_id_condition(model)
+    }
+}
+
+/// Columns the server maintains on every update.
+pub fn get_update_assignments(model: &InternalModel) -> Vec<&'static str> {
+    let mut assignments = vec![];
+    if model.options.versioned {
+        assignments.push("version = version + 1");
+    }
+    if model.options.timestamps {
+        assignments.push("updated_at = CURRENT_TIMESTAMP");
+    }
+
+    assignments
+}
+
+/// The number of placeholders taken by `get_update_condition`.
+pub fn get_update_condition_params(model: &InternalModel) -> usize {
+    if model.options.versioned {
+        2
+    } else {
+        1
+    }
+}
+
+pub fn create_get_entity(model: &InternalModel, include_deleted: bool) -> String {
+    let condition = if include_deleted {
+        format!("{} = $1{}", get_id_column(model), get_tenancy_condition(model))
+    } else {
+        get_id_condition(model)
     };
+    format!("SELECT {} FROM {} WHERE {}", get_select_columns(model), get_table_name(model), condition)
+}
 
-    format!("UPDATE public.{} SET {} WHERE id = $1", model.name.to_lowercase(), fields)
+/// The rows of `target` whose `key` property is one of the values in `$1`, to embed them with `?include=`. The values
+/// are bound as text and cast to the type of the column.
+pub fn create_get_related(target: &InternalModel, key: &str) -> String {
+    let (column, sql_type) = get_related_column(target, key);
+    format!(
+        "SELECT {} FROM {} WHERE {} = ANY(CAST($1 AS {}[])){}",
+        get_select_columns(target),
+        get_table_name(target),
+        column,
+        sql_type,
+        get_visible_condition(target)
+    )
 }
 
+/// `create_get_related` for SQLite, the values are bound as json array.
+pub fn create_sqlite_get_related(target: &InternalModel, key: &str) -> String {
+    let (column, sql_type) = get_related_column(target, key);
+    format!(
+        "SELECT {} FROM {} WHERE {} IN (SELECT CAST(value AS {}) FROM json_each($1)){}",
+        get_select_columns(target),
+        get_table_name(target),
+        column,
+        sql_type,
+        get_visible_condition(target)
+    )
+}
+
+fn get_related_column(target: &InternalModel, key: &str) -> (String, String) {
+    match target.properties.as_ref().and_then(|properties| properties.get(key)) {
+        Some(property) => (quote_identifier(property.column_name(key)), get_column_type(property)),
+        None => (quote_identifier(key), "text".to_string()),
+    }
+}
+
+/// The `target` the row with the id in `$1` refers to by its `foreign_key` property.
+pub fn create_get_belongs_to(model: &InternalModel, foreign_key: &str, target: &InternalModel) -> String {
+    format!(
+        "SELECT {} FROM {} WHERE {} = (SELECT {} FROM {} WHERE {}){}",
+        get_select_columns(target),
+        get_table_name(target),
+        get_id_column(target),
+        get_column_name(model, foreign_key),
+        get_table_name(model),
+        get_id_condition(model),
+        get_visible_condition(target)
+    )
+}
+
+/// Points the `foreign_key` property of the row with the id in `$1` to the id in `$2`.
+pub fn create_set_foreign_key(model: &InternalModel, foreign_key: &str) -> String {
+    let mut assignments = vec![format!("{} = $2", get_column_name(model, foreign_key))];
+    assignments.extend(get_update_assignments(model).iter().map(|assignment| assignment.to_string()));
+    format!(
+        "UPDATE {} SET {} WHERE {} RETURNING {}",
+        get_table_name(model),
+        assignments.join(", "),
+        get_id_condition(model),
+        get_id_column(model)
+    )
+}
+
+fn get_column_name(model: &InternalModel, key: &str) -> String {
+    let column = model
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get(key))
+        .map(|property| property.column_name(key))
+        .unwrap_or(key);
+    quote_identifier(column)
+}
+
+/// Returns the id of the deleted row, so a delete of a missing row can be told apart.
 pub fn create_delete_entity(model: &InternalModel) -> String {
-    format!("DELETE FROM public.{} WHERE id = ?", model.name.to_lowercase())
+    if model.options.soft_delete {
+        let mut assignments = vec!["deleted_at = CURRENT_TIMESTAMP"];
+        assignments.extend(get_update_assignments(model));
+        return format!(
+            "UPDATE {} SET {} WHERE {} RETURNING {}",
+            get_table_name(model),
+            assignments.join(", "),
+            get_update_condition(model),
+            get_id_column(model)
+        );
+    }
+    format!(
+        "DELETE FROM {} WHERE {} RETURNING {}",
+        get_table_name(model),
+        get_update_condition(model),
+        get_id_column(model)
+    )
 }
 
 pub fn create_create_table(model: &InternalModel) -> String {
-    let fields: String = match &model.properties {
-        None => "".to_string(),
+    let fields: Vec<String> = match &model.properties {
+        None => vec![],
         Some(properties) => properties
             .into_iter()
-            .map(|(key, value)| {
-                format!("{} {}", key, get_matching_sql_datatype(&value).unwrap())
-            })
-            .collect::<Vec<_>>()
-            .join(", "),
+            .map(|(key, property)| format!("{} {}", quote_identifier(property.column_name(key)), get_column_definition(key, property)))
+            .collect(),
+    };
+
+    let mut fields = fields;
+    if let Some(document) = get_search_document(model) {
+        fields.push(format!(
+            "search_vector tsvector GENERATED ALWAYS AS (to_tsvector({}, {})) STORED",
+            get_search_config(model),
+            document
+        ));
+    }
+
+    format!("CREATE TABLE IF NOT EXISTS {} ({});", get_table_name(model), fields.join(", "))
+}
+
+/// The searchable columns as one text, `None` if the model has none.
+fn get_search_document(model: &InternalModel) -> Option<String> {
+    let columns: Vec<String> = model
+        .properties
+        .iter()
+        .flatten()
+        .filter(|(_, property)| property.searchable)
+        .map(|(key, property)| format!("coalesce({}, '')", quote_identifier(property.column_name(key))))
+        .collect();
+    if columns.is_empty() {
+        None
+    } else {
+        Some(columns.join(" || ' ' || "))
+    }
+}
+
+/// The text search configuration as sql literal.
+fn get_search_config(model: &InternalModel) -> String {
+    let config = model.options.search_config.as_deref().unwrap_or("simple");
+    format!("'{}'", config.replace('\'', "''"))
+}
+
+/// Ranks the rows matching the web search syntax query in `$1` by relevance, with a highlighted snippet of the
+/// searchable columns and the total number of matches. `$2` and `$3` are limit and offset.
+pub fn create_search_entities(model: &InternalModel) -> String {
+    let config = get_search_config(model);
+    let order = match model.properties.as_ref().and_then(|properties| properties.get("id")) {
+        Some(id) => format!("search_rank DESC, {}", quote_identifier(id.column_name("id"))),
+        None => "search_rank DESC".to_string(),
+    };
+    format!(
+        "SELECT {}, ts_rank(search_vector, search_query) AS search_rank, ts_headline({}, {}, search_query) AS search_snippet, \
+         count(*) OVER () AS search_total FROM {}, websearch_to_tsquery({}, $1) search_query \
+         WHERE search_vector @@ search_query{} ORDER BY {} LIMIT $2 OFFSET $3",
+        get_select_columns(model),
+        config,
+        get_search_document(model).unwrap_or_else(|| "''".to_string()),
+        get_table_name(model),
+        config,
+        get_visible_condition(model),
+        order
+    )
+}
+
+pub fn is_searchable(model: &InternalModel) -> bool {
+    get_search_document(model).is_some()
+}
+
+pub fn create_create_indexes(model: &InternalModel) -> Vec<String> {
+    let mut indexes: Vec<String> = model
+        .indexes
+        .iter()
+        .map(|index| create_create_index(index, &get_index_name(model, index), &get_table_name(model)))
+        .collect();
+    if is_searchable(model) {
+        indexes.push(format!(
+            "CREATE INDEX IF NOT EXISTS {}_search_vector_idx ON {} USING GIN (search_vector);",
+            get_unqualified_table_name(model),
+            get_table_name(model)
+        ));
+    }
+
+    indexes
+}
+
+fn get_index_name(model: &InternalModel, index: &Index) -> String {
+    let suffix = if index.unique { "key" } else { "idx" };
+    index
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{}_{}_{}", get_unqualified_table_name(model), index.columns.join("_"), suffix))
+}
+
+fn create_create_index(index: &Index, name: &str, table: &str) -> String {
+    let predicate = index.predicate.as_ref().map(|predicate| format!(" WHERE {}", predicate)).unwrap_or_default();
+    format!(
+        "CREATE {}INDEX IF NOT EXISTS {} ON {} ({}){};",
+        if index.unique { "UNIQUE " } else { "" },
+        name,
+        table,
+        index.columns.iter().map(|column| quote_identifier(column)).collect::<Vec<_>>().join(", "),
+        predicate
+    )
+}
+
+/// Models whose api tests need Postgres: tenant scoped ones, the history triggers, full text search and defaults other
+/// than `now()`, which are Postgres expressions. The others also get a table in the SQLite database of the tests.
+pub fn needs_postgres(model: &InternalModel) -> bool {
+    model.options.tenancy.is_some()
+        || model.options.history
+        || is_searchable(model)
+        || model
+            .properties
+            .iter()
+            .flatten()
+            .any(|(_, property)| property.server_default.as_deref().is_some_and(|default| default != "now()"))
+}
+
+/// The table of `create_create_table` and its indexes in the SQLite database the api tests run on. The schemas are
+/// attached databases, so the table keeps its name. Arrays are stored as json text and a generated id is the rowid.
+pub fn create_sqlite_create_table(model: &InternalModel) -> Vec<String> {
+    let fields: Vec<String> = model
+        .properties
+        .iter()
+        .flatten()
+        .map(|(key, property)| {
+            let mut definition = format!("{} ", quote_identifier(property.column_name(key)));
+            if is_generated_id(key, property) {
+                definition.push_str("integer PRIMARY KEY");
+                return definition;
+            }
+            definition.push_str(&if property.is_array { "text".to_string() } else { get_column_type(property) });
+            if !property.is_optional() {
+                definition.push_str(" NOT NULL");
+            }
+            // `needs_postgres` leaves `now()` as the only server default
+            match (&property.server_default, &property.default) {
+                (Some(_), _) => definition.push_str(" DEFAULT CURRENT_TIMESTAMP"),
+                (None, Some(default)) => definition.push_str(&format!(" DEFAULT {}", get_sql_literal(default))),
+                (None, None) => {}
+            }
+            definition
+        })
+        .collect();
+
+    let mut statements = vec![format!("CREATE TABLE IF NOT EXISTS {} ({});", get_table_name(model), fields.join(", "))];
+    statements.extend(model.indexes.iter().map(|index| {
+        let name = format!("{}.{}", get_schema_name(model), get_index_name(model, index));
+        create_create_index(index, &name, &quote_identifier(&get_unqualified_table_name(model)))
+    }));
+    statements
+}
+
+/// All tables and their indexes, written as the initial migration of the generated project.
+pub fn create_migration(models: &InternalModels) -> String {
+    let mut sql = "".to_string();
+    for model in models {
+        sql.push_str(&create_create_table(model));
+        sql.push('\n');
+        for statement in create_create_indexes(model).iter().chain(create_create_history(model).iter()) {
+            sql.push_str(statement);
+            sql.push('\n');
+        }
+    }
+
+    sql
+}
+
+fn get_history_table_name(model: &InternalModel) -> String {
+    format!("{}.{}", get_schema_name(model), quote_identifier(&format!("{}_history", get_unqualified_table_name(model))))
+}
+
+/// The history table of a model and the trigger filling it. Old and new rows are stored as json without the columns that
+/// are never sent to clients, the actor is taken from the `crudify.actor` setting of the transaction if there is one.
+pub fn create_create_history(model: &InternalModel) -> Vec<String> {
+    if !model.options.history {
+        return vec![];
+    }
+    let table = get_table_name(model);
+    let history_table = get_history_table_name(model);
+    let unqualified = get_unqualified_table_name(model);
+    let function = format!("{}.{}_history_fn", get_schema_name(model), unqualified);
+    let id_type = model
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get("id"))
+        .map(get_column_type)
+        .unwrap_or_else(|| "bigint".to_string());
+    let mut hidden: Vec<String> = model
+        .properties
+        .iter()
+        .flatten()
+        .filter(|(_, property)| !property.is_readable())
+        .map(|(key, property)| property.column_name(key).to_string())
+        .collect();
+    if is_searchable(model) {
+        hidden.push("search_vector".to_string());
+    }
+    let hide = if hidden.is_empty() {
+        "".to_string()
+    } else {
+        format!(" - '{{{}}}'::text[]", hidden.join(","))
+    };
+    let id = get_id_column(model);
+
+    vec![
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (history_id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, entity_id {}, \
+             operation text NOT NULL, changed_by text NOT NULL, changed_at timestamptz NOT NULL DEFAULT now(), old_row jsonb, new_row jsonb);",
+            history_table, id_type
+        ),
+        format!(
+            "CREATE INDEX IF NOT EXISTS {}_history_entity_id_idx ON {} (entity_id);",
+            unqualified, history_table
+        ),
+        format!(
+            "CREATE OR REPLACE FUNCTION {0}() RETURNS trigger AS $$ BEGIN \
+             INSERT INTO {1} (entity_id, operation, changed_by, old_row, new_row) VALUES (\
+             CASE WHEN TG_OP = 'DELETE' THEN OLD.{2} ELSE NEW.{2} END, TG_OP, \
+             coalesce(nullif(current_setting('crudify.actor', true), ''), current_user), \
+             CASE WHEN TG_OP = 'INSERT' THEN NULL ELSE to_jsonb(OLD){3} END, \
+             CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE to_jsonb(NEW){3} END); \
+             RETURN NULL; END; $$ LANGUAGE plpgsql;",
+            function, history_table, id, hide
+        ),
+        format!("DROP TRIGGER IF EXISTS {}_history_trigger ON {};", unqualified, table),
+        format!(
+            "CREATE TRIGGER {0}_history_trigger AFTER INSERT OR UPDATE OR DELETE ON {1} FOR EACH ROW EXECUTE FUNCTION {2}();",
+            unqualified, table, function
+        ),
+    ]
+}
+
+/// The recorded rows of other tenants or owners are hidden like the rows themselves.
+pub fn create_get_history(model: &InternalModel) -> String {
+    let tenancy = match model.options.tenancy {
+        Some(tenancy) => format!(" AND coalesce(new_row, old_row) ->> '{}' = {}", tenancy.column(), tenancy.current_value()),
+        None => "".to_string(),
     };
+    format!(
+        "SELECT history_id, operation, changed_by, changed_at, old_row, new_row FROM {} WHERE entity_id = $1{} ORDER BY history_id",
+        get_history_table_name(model),
+        tenancy
+    )
+}
 
-    format!("CREATE TABLE IF NOT EXISTS public.{} ({});", model.name.to_lowercase(), fields)
+/// The sql type of a single value of the property, without the `[]` of array properties.
+pub fn get_column_type(property: &Property) -> String {
+    match &property.storage.sql_type {
+        Some(sql_type) => sql_type.to_string(),
+        None => get_matching_sql_datatype(&property.data_type).to_string(),
+    }
 }
 
-fn get_matching_sql_datatype(data_type: &RustDataType) -> Result<&'static str, SqlConverterError<'static>> {
+fn get_column_definition(key: &str, property: &Property) -> String {
+    let mut definition = get_column_type(property);
+    if property.is_array {
+        definition.push_str("[]");
+    }
+    if is_generated_id(key, property) {
+        definition.push_str(" GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY");
+        return definition;
+    }
+    if !property.is_optional() {
+        definition.push_str(" NOT NULL");
+    }
+    if let Some(default) = get_default_value(property) {
+        definition.push_str(&format!(" DEFAULT {}", default));
+    }
+
+    definition
+}
+
+/// The sql expression for a property default, a server side `x-crudify-server-default` takes precedence over a spec `default`.
+pub fn get_default_value(property: &Property) -> Option<String> {
+    match (&property.server_default, &property.default) {
+        (Some(server_default), _) => Some(server_default.to_string()),
+        (None, Some(default)) if property.is_array => match default {
+            Value::Array(values) => Some(format!(
+                "ARRAY[{}]::{}[]",
+                values.iter().map(get_sql_literal).collect::<Vec<_>>().join(", "),
+                get_matching_sql_datatype(&property.data_type)
+            )),
+            _ => Some(get_sql_literal(default)),
+        },
+        (None, Some(default)) => Some(get_sql_literal(default)),
+        (None, None) => None,
+    }
+}
+
+fn get_sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => b.to_string().to_uppercase(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Array(_) | Value::Object(_) => format!("'{}'", value.to_string().replace('\'', "''")),
+    }
+}
+
+/// A `readOnly` integer `id` is generated by the database.
+fn is_generated_id(key: &str, property: &Property) -> bool {
+    key == "id" && !property.is_writable() && matches!(property.data_type, RustDataType::I32 | RustDataType::I64)
+}
+
+fn get_matching_sql_datatype(data_type: &RustDataType) -> &'static str {
     match data_type {
-        RustDataType::U8 => Ok("smallint"),
-        RustDataType::I32 => Ok("integer"),
-        RustDataType::I64 => Ok("bigint"),
-        RustDataType::F32 => Ok("real"),
-        RustDataType::F64 => Ok("doubl precision"),
-        RustDataType::String => Ok("text"),
-        RustDataType::Bool => Ok("boolean"),
-        RustDataType::Date => Ok("date"),
-        RustDataType::DateTime => Ok("datetime"),
+        RustDataType::U8 => "smallint",
+        RustDataType::I32 => "integer",
+        RustDataType::I64 => "bigint",
+        RustDataType::F32 => "real",
+        RustDataType::F64 => "double precision",
+        RustDataType::String => "text",
+        RustDataType::Bool => "boolean",
+        RustDataType::Date => "date",
+        RustDataType::DateTime => "timestamptz",
+    }
+}
+
+/// Builds an INSERT for the columns a client actually sent, so the database defaults apply to the rest.
+pub const INSERT_QUERY_FN: &str = r#"
+fn insert_query(table: &str, columns: &[&str], returning: &str) -> String {
+    if columns.is_empty() {
+        return format!("INSERT INTO {} DEFAULT VALUES RETURNING {}", table, returning);
+    }
+    let values = (1..=columns.len()).map(|idx| format!("${}", idx)).collect::<Vec<_>>().join(", ");
+    format!("INSERT INTO {} ({}) VALUES ({}) RETURNING {}", table, columns.join(", "), values, returning)
+}
+"#;
+
+/// Builds an upsert for the columns a client actually sent, see `create_upsert_entity`. Columns that weren't sent keep
+/// their value when the row is updated.
+pub const UPSERT_QUERY_FN: &str = r#"
+fn upsert_query(
+    table: &str,
+    columns: &[&str],
+    conflict: &str,
+    key: &[&str],
+    assignments: &[&str],
+    condition: &str,
+    returning: &str,
+) -> String {
+    let values = (1..=columns.len()).map(|idx| format!("${}", idx)).collect::<Vec<_>>().join(", ");
+    let mut set = columns
+        .iter()
+        .filter(|column| !key.contains(column))
+        .map(|column| format!("{0} = EXCLUDED.{0}", column))
+        .collect::<Vec<_>>();
+    set.extend(assignments.iter().map(|assignment| assignment.to_string()));
+    if set.is_empty() {
+        set = key.iter().map(|column| format!("{0} = EXCLUDED.{0}", column)).collect();
+    }
+    format!(
+        "INSERT INTO {} AS existing ({}) VALUES ({}) ON CONFLICT {} DO UPDATE SET {}{} RETURNING {}, xmax = 0 AS inserted",
+        table,
+        columns.join(", "),
+        values,
+        conflict,
+        set.join(", "),
+        condition,
+        returning
+    )
+}
+"#;
+
+/// Builds a multi-row INSERT, `rows` tells for each row which of the `columns` it sends. The others get their DEFAULT.
+pub const BULK_INSERT_QUERY_FN: &str = r#"
+fn bulk_insert_query(table: &str, columns: &[&str], rows: &[Vec<bool>], returning: &str) -> String {
+    if columns.is_empty() {
+        return format!("INSERT INTO {} SELECT FROM generate_series(1, {}) RETURNING {}", table, rows.len(), returning);
+    }
+    let mut placeholder = 0;
+    let values = rows
+        .iter()
+        .map(|sent| {
+            let values = sent
+                .iter()
+                .map(|sent| {
+                    if *sent {
+                        placeholder += 1;
+                        format!("${}", placeholder)
+                    } else {
+                        "DEFAULT".to_string()
+                    }
+                })
+                .collect::<Vec<_>>();
+            format!("({})", values.join(", "))
+        })
+        .collect::<Vec<_>>();
+    format!("INSERT INTO {} ({}) VALUES {} RETURNING {}", table, columns.join(", "), values.join(", "), returning)
+}
+"#;
+
+/// Builds an UPDATE of the columns a client sent in a PATCH, an empty patch only selects the row. The values are bound
+/// from placeholder `first` on, after those of the `condition` from `get_update_condition`. The `assignments` of
+/// `get_update_assignments` are added as they are.
+pub const UPDATE_QUERY_FN: &str = r#"
+fn update_query(table: &str, columns: &[&str], first: usize, assignments: &[&str], condition: &str, returning: &str) -> String {
+    if columns.is_empty() {
+        return format!("SELECT {} FROM {} WHERE {}", returning, table, condition);
+    }
+    let mut fields = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| format!("{} = ${}", column, idx + first))
+        .collect::<Vec<_>>();
+    fields.extend(assignments.iter().map(|assignment| assignment.to_string()));
+    format!("UPDATE {} SET {} WHERE {} RETURNING {}", table, fields.join(", "), condition, returning)
+}
+"#;
+
+/// Parameters of a full text search, `q` is required. Builds on `LIST_QUERY_FN` for the limits and the page.
+pub const SEARCH_QUERY_FN: &str = r#"
+#[derive(Serialize)]
+struct SearchHit<T> {
+    item: T,
+    rank: f32,
+    snippet: String,
+}
+
+struct SearchQuery {
+    q: String,
+    limit: i64,
+    offset: i64,
+}
+
+fn search_query(params: &[(String, String)]) -> Result<SearchQuery, AppError> {
+    let mut search = SearchQuery { q: "".to_string(), limit: DEFAULT_LIMIT, offset: 0 };
+    for (key, value) in params {
+        match key.as_str() {
+            "q" => search.q = value.to_string(),
+            "limit" => {
+                search.limit = value
+                    .parse()
+                    .ok()
+                    .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+                    .ok_or_else(|| AppError::new_bad_request(format!("limit must be between 1 and {}", MAX_LIMIT)))?;
+            }
+            "offset" => {
+                search.offset = value
+                    .parse()
+                    .ok()
+                    .filter(|offset: &i64| *offset >= 0)
+                    .ok_or_else(|| AppError::new_bad_request("offset must be a positive number".to_string()))?;
+            }
+            _ => return Err(AppError::new_bad_request(format!("Unknown search parameter {}", key))),
+        }
+    }
+    if search.q.trim().is_empty() {
+        return Err(AppError::new_bad_request("q is required".to_string()));
+    }
+    Ok(search)
+}
+"#;
+
+/// Adds the filters, sorting and paging of a list request to the queries of `create_get_all_entities` and
+/// `create_count_entities`. Field names are checked against the columns of the model and every value is bound, filter
+/// values are sent as text and cast to the type of their column.
+pub const LIST_QUERY_FN: &str = r#"
+/// A property a list request may filter and sort by.
+struct ListColumn {
+    name: &'static str,
+    column: &'static str,
+    sql_type: &'static str,
+    is_text: bool,
+}
+
+enum ListBind {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The filters are bound to both queries, the cursor, limit and offset only to `select`.
+struct ListQuery {
+    select: String,
+    count: String,
+    binds: Vec<ListBind>,
+    cursor: Option<String>,
+    limit: i64,
+    offset: i64,
+    keyset: bool,
+}
+
+#[derive(Serialize)]
+struct Page<T> {
+    items: Vec<T>,
+    total: i64,
+    next_cursor: Option<String>,
+}
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        "".to_string()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
     }
 }
 
+/// A filter value as the type of its column. SQLite keeps booleans as 1 and 0, dates and timestamps as text.
+fn cast(value: &str, sql_type: &str) -> String {
+    match sql_type {
+        "boolean" if SQLITE => format!("({} = 'true')", value),
+        "date" | "timestamptz" if SQLITE => value.to_string(),
+        _ => format!("CAST({} AS {})", value, sql_type),
+    }
+}
+
+/// Understands `limit`, `offset`, `cursor`, `sort=field,-field`, `include_deleted=true` and filters like `field=value`
+/// or `field[op]=value` with `eq`, `lt`, `gt`, `like` and `in`, the latter taking a comma separated list. The `tenancy`
+/// condition applies to every request.
+fn list_query(
+    select: &str,
+    count: &str,
+    tenancy: Option<&str>,
+    not_deleted: Option<&str>,
+    columns: &[ListColumn],
+    cursor_column: Option<&str>,
+    params: &[(String, String)],
+) -> Result<ListQuery, AppError> {
+    let find_column = |name: &str| {
+        columns
+            .iter()
+            .find(|column| column.name == name)
+            .ok_or_else(|| AppError::new_bad_request(format!("Unknown field: {}", name)))
+    };
+    let mut conditions = vec![];
+    let mut include_deleted = false;
+    let mut binds = vec![];
+    let mut order = vec![];
+    let mut cursor = None;
+    let mut limit = DEFAULT_LIMIT;
+    let mut offset = None;
+    for (key, value) in params {
+        match key.as_str() {
+            "limit" => {
+                limit = value
+                    .parse()
+                    .ok()
+                    .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+                    .ok_or_else(|| AppError::new_bad_request(format!("limit must be between 1 and {}", MAX_LIMIT)))?;
+            }
+            "offset" => {
+                let value = value
+                    .parse()
+                    .ok()
+                    .filter(|offset: &i64| *offset >= 0)
+                    .ok_or_else(|| AppError::new_bad_request("offset must be a positive number".to_string()))?;
+                offset = Some(value);
+            }
+            "cursor" => cursor = Some(value.to_string()),
+            "include_deleted" => include_deleted = value == "true",
+            "include" => {}
+            "sort" => {
+                for field in value.split(',').filter(|field| !field.is_empty()) {
+                    let (name, direction) = match field.strip_prefix('-') {
+                        Some(name) => (name, "DESC"),
+                        None => (field, "ASC"),
+                    };
+                    order.push(format!("{} {}", find_column(name)?.column, direction));
+                }
+            }
+            _ => {
+                let (name, operator) = key
+                    .strip_suffix(']')
+                    .and_then(|key| key.split_once('['))
+                    .unwrap_or((key.as_str(), "eq"));
+                let column = find_column(name)?;
+                let placeholder = binds.len() + 1;
+                let bound = cast(&format!("${}", placeholder), column.sql_type);
+                let condition = match operator {
+                    "eq" => format!("{} = {}", column.column, bound),
+                    "lt" => format!("{} < {}", column.column, bound),
+                    "gt" => format!("{} > {}", column.column, bound),
+                    "like" if column.is_text => format!("{} LIKE ${}", column.column, placeholder),
+                    "in" if SQLITE => format!(
+                        "{} IN (SELECT {} FROM json_each(${}))",
+                        column.column,
+                        cast("value", column.sql_type),
+                        placeholder
+                    ),
+                    "in" => format!("{} = ANY(CAST(${} AS {}[]))", column.column, placeholder, column.sql_type),
+                    _ => return Err(AppError::new_bad_request(format!("Unsupported filter {} on {}", operator, name))),
+                };
+                conditions.push(condition);
+                binds.push(match operator {
+                    "in" => ListBind::Many(value.split(',').map(|value| value.to_string()).collect()),
+                    _ => ListBind::One(value.to_string()),
+                });
+            }
+        }
+    }
+
+    if let Some(not_deleted) = not_deleted.filter(|_| !include_deleted) {
+        conditions.insert(0, not_deleted.to_string());
+    }
+    if let Some(tenancy) = tenancy {
+        conditions.insert(0, tenancy.to_string());
+    }
+    let count = format!("{}{}", count, where_clause(&conditions));
+    let keyset_column = match cursor_column {
+        Some(name) if order.is_empty() => Some(find_column(name)?),
+        _ => None,
+    };
+    if cursor.is_some() {
+        let column = keyset_column
+            .filter(|_| offset.is_none())
+            .ok_or_else(|| AppError::new_bad_request("cursor can't be combined with sort or offset".to_string()))?;
+        conditions.push(format!("{} > {}", column.column, cast(&format!("${}", binds.len() + 1), column.sql_type)));
+    }
+    if let Some(column) = keyset_column {
+        order.push(format!("{} ASC", column.column));
+    }
+    let order_by = if order.is_empty() {
+        "".to_string()
+    } else {
+        format!(" ORDER BY {}", order.join(", "))
+    };
+    let limit_placeholder = binds.len() + if cursor.is_some() { 2 } else { 1 };
+    let select = format!(
+        "{}{}{} LIMIT ${} OFFSET ${}",
+        select,
+        where_clause(&conditions),
+        order_by,
+        limit_placeholder,
+        limit_placeholder + 1
+    );
+
+    Ok(ListQuery {
+        select,
+        count,
+        binds,
+        cursor,
+        limit,
+        offset: offset.unwrap_or(0),
+        keyset: keyset_column.is_some(),
+    })
+}
+"#;
+
 #[cfg(test)]
 mod tests {
     use indexmap::{indexmap, IndexMap};
+    use serde_json::json;
 
     use crate::json_converter::RustDataType;
+    use crate::{Index, ModelOptions};
 
     use super::*;
 
     impl InternalModel {
         fn new(name: String) -> InternalModel {
-            InternalModel { name, properties: None }
+            InternalModel {
+                name,
+                description: None,
+                properties: None,
+                relations: vec![],
+                indexes: vec![],
+                options: ModelOptions::default(),
+            }
         }
 
         fn new_with_props(name: String, props: IndexMap<String, RustDataType>) -> InternalModel {
             InternalModel {
-                name,
-                properties: Some(props),
+                properties: Some(props.into_iter().map(|(key, data_type)| (key, Property::new(data_type))).collect()),
+                ..InternalModel::new(name)
             }
         }
     }
 
+    fn read_only_id_and_password_model() -> InternalModel {
+        let mut model = InternalModel::new_with_props(
+            "User".to_string(),
+            indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String,
+            "password".to_string() => RustDataType::String},
+        );
+        let properties = model.properties.as_mut().unwrap();
+        properties.get_mut("id").unwrap().read_only = true;
+        properties.get_mut("password").unwrap().format = Some("password".to_string());
+        model
+    }
+
     #[test]
     fn test_create_table() {
         let props = indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String};
-        let expected = "CREATE TABLE IF NOT EXISTS public.order (id bigint, name text);";
+        let expected = "CREATE TABLE IF NOT EXISTS public.\"order\" (\"id\" bigint, \"name\" text);";
         assert_eq!(
             expected,
             create_create_table(&InternalModel::new_with_props("Order".to_string(), props))
         );
     }
 
+    #[test]
+    fn test_create_table_with_required_and_array_properties() {
+        let mut model = InternalModel::new_with_props(
+            "Pet".to_string(),
+            indexmap! {"name".to_string() => RustDataType::String, "photoUrls".to_string() => RustDataType::String},
+        );
+        let properties = model.properties.as_mut().unwrap();
+        properties.get_mut("name").unwrap().required = true;
+        properties.get_mut("photoUrls").unwrap().is_array = true;
+
+        let expected = "CREATE TABLE IF NOT EXISTS public.\"pet\" (\"name\" text NOT NULL, \"photoUrls\" text[]);";
+        assert_eq!(expected, create_create_table(&model));
+    }
+
+    #[test]
+    fn test_create_table_with_defaults() {
+        let mut model = InternalModel::new_with_props(
+            "Order".to_string(),
+            indexmap! {"status".to_string() => RustDataType::String, "quantity".to_string() => RustDataType::I32,
+            "complete".to_string() => RustDataType::Bool, "shipDate".to_string() => RustDataType::DateTime},
+        );
+        let properties = model.properties.as_mut().unwrap();
+        properties.get_mut("status").unwrap().default = Some(json!("it's placed"));
+        properties.get_mut("quantity").unwrap().default = Some(json!(1));
+        properties.get_mut("complete").unwrap().default = Some(json!(false));
+        properties.get_mut("shipDate").unwrap().server_default = Some("now()".to_string());
+
+        let expected = "CREATE TABLE IF NOT EXISTS public.\"order\" (\"status\" text DEFAULT 'it''s placed', \"quantity\" integer DEFAULT 1, \
+                        \"complete\" boolean DEFAULT FALSE, \"shipDate\" timestamptz DEFAULT now());";
+        assert_eq!(expected, create_create_table(&model));
+    }
+
+    #[test]
+    fn test_camel_case_columns_keep_their_case() {
+        let mut model = InternalModel::new_with_props(
+            "Order".to_string(),
+            indexmap! {"id".to_string() => RustDataType::I64, "petId".to_string() => RustDataType::I64},
+        );
+        model.options.upsert_key = Some(vec!["petId".to_string()]);
+        assert_eq!("CREATE TABLE IF NOT EXISTS public.\"order\" (\"id\" bigint, \"petId\" bigint);", create_create_table(&model));
+        assert_eq!(
+            "INSERT INTO public.\"order\" (\"id\", \"petId\") VALUES ($1, $2) RETURNING \"id\", \"petId\"",
+            create_create_entity(&model)
+        );
+        assert_eq!(
+            "UPDATE public.\"order\" SET \"id\" = $1, \"petId\" = $2 WHERE \"id\" = $1 RETURNING \"id\", \"petId\"",
+            create_update_entity(&model)
+        );
+        assert!(create_upsert_entity(&model).contains("ON CONFLICT (\"petId\") DO UPDATE SET \"id\" = EXCLUDED.\"id\""));
+    }
+
+    #[test]
+    fn test_array_default_value() {
+        let mut property = Property::new(RustDataType::String);
+        property.is_array = true;
+        property.default = Some(json!(["a", "b"]));
+        assert_eq!(Some("ARRAY['a', 'b']::text[]".to_string()), get_default_value(&property));
+    }
+
+    fn model_with_extensions() -> InternalModel {
+        let mut model = InternalModel::new_with_props(
+            "Order".to_string(),
+            indexmap! {"id".to_string() => RustDataType::I64, "petId".to_string() => RustDataType::I64},
+        );
+        let properties = model.properties.as_mut().unwrap();
+        properties.get_mut("petId").unwrap().storage.column_name = Some("pet_id".to_string());
+        let mut deleted_at = Property::new(RustDataType::DateTime);
+        deleted_at.read_only = true;
+        properties.insert("deleted_at".to_string(), deleted_at);
+        model.options.table_name = Some("store.orders".to_string());
+        model.options.soft_delete = true;
+        model
+    }
+
+    #[test]
+    fn test_table_name_override() {
+        let mut model = InternalModel::new("Order".to_string());
+        model.options.table_name = Some("orders".to_string());
+        assert_eq!("public.\"orders\"", get_table_name(&model));
+        model.options.table_name = Some("store.orders".to_string());
+        assert_eq!("store.\"orders\"", get_table_name(&model));
+    }
+
+    #[test]
+    fn test_create_table_with_extensions() {
+        let mut model = model_with_extensions();
+        model.properties.as_mut().unwrap().get_mut("id").unwrap().storage.sql_type = Some("numeric(20)".to_string());
+        let expected = "CREATE TABLE IF NOT EXISTS store.\"orders\" (\"id\" numeric(20), \"pet_id\" bigint, \"deleted_at\" timestamptz);";
+        assert_eq!(expected, create_create_table(&model));
+    }
+
+    #[test]
+    fn test_entity_queries_with_extensions() {
+        let model = model_with_extensions();
+        assert_eq!("SELECT \"id\", \"pet_id\", \"deleted_at\" FROM store.\"orders\"", create_get_all_entities(&model));
+        assert_eq!("SELECT count(*) FROM store.\"orders\"", create_count_entities(&model));
+        assert_eq!(Some("deleted_at IS NULL"), get_not_deleted_filter(&model));
+        assert_eq!(
+            "SELECT \"id\", \"pet_id\", \"deleted_at\" FROM store.\"orders\" WHERE \"id\" = $1 AND deleted_at IS NULL",
+            create_get_entity(&model, false)
+        );
+        assert_eq!(
+            "SELECT \"id\", \"pet_id\", \"deleted_at\" FROM store.\"orders\" WHERE \"id\" = $1",
+            create_get_entity(&model, true)
+        );
+        assert_eq!(
+            "INSERT INTO store.\"orders\" (\"id\", \"pet_id\") VALUES ($1, $2) RETURNING \"id\", \"pet_id\", \"deleted_at\"",
+            create_create_entity(&model)
+        );
+        assert_eq!(
+            "UPDATE store.\"orders\" SET \"id\" = $1, \"pet_id\" = $2 WHERE \"id\" = $1 AND deleted_at IS NULL RETURNING \"id\", \"pet_id\", \"deleted_at\"",
+            create_update_entity(&model)
+        );
+        assert_eq!(
+            "UPDATE store.\"orders\" SET deleted_at = CURRENT_TIMESTAMP WHERE \"id\" = $1 AND deleted_at IS NULL RETURNING \"id\"",
+            create_delete_entity(&model)
+        );
+    }
+
+    #[test]
+    fn test_create_indexes() {
+        let mut model = model_with_extensions();
+        model.indexes = vec![
+            Index {
+                name: None,
+                columns: vec!["pet_id".to_string()],
+                unique: false,
+                predicate: None,
+            },
+            Index {
+                name: Some("order_pet".to_string()),
+                columns: vec!["id".to_string(), "pet_id".to_string()],
+                unique: true,
+                predicate: None,
+            },
+        ];
+        assert_eq!(
+            vec![
+                "CREATE INDEX IF NOT EXISTS orders_pet_id_idx ON store.\"orders\" (\"pet_id\");",
+                "CREATE UNIQUE INDEX IF NOT EXISTS order_pet ON store.\"orders\" (\"id\", \"pet_id\");"
+            ],
+            create_create_indexes(&model)
+        );
+    }
+
+    #[test]
+    fn test_create_partial_index() {
+        let mut model = model_with_extensions();
+        model.indexes = vec![Index {
+            name: None,
+            columns: vec!["pet_id".to_string()],
+            unique: true,
+            predicate: Some("deleted_at IS NULL".to_string()),
+        }];
+        assert_eq!(
+            vec!["CREATE UNIQUE INDEX IF NOT EXISTS orders_pet_id_key ON store.\"orders\" (\"pet_id\") WHERE deleted_at IS NULL;"],
+            create_create_indexes(&model)
+        );
+    }
+
+    #[test]
+    fn test_create_migration() {
+        let mut model = InternalModel::new_with_props("Order".to_string(), indexmap! {"id".to_string() => RustDataType::I64});
+        model.indexes = vec![Index {
+            name: None,
+            columns: vec!["id".to_string()],
+            unique: true,
+            predicate: None,
+        }];
+        let expected = "CREATE TABLE IF NOT EXISTS public.\"order\" (\"id\" bigint);\n\
+                        CREATE UNIQUE INDEX IF NOT EXISTS order_id_key ON public.\"order\" (\"id\");\n\
+                        CREATE TABLE IF NOT EXISTS public.\"tag\" ();\n";
+        assert_eq!(expected, create_migration(&vec![model, InternalModel::new("Tag".to_string())]));
+    }
+
+    #[test]
+    fn test_create_history() {
+        let mut model = read_only_id_and_password_model();
+        assert!(create_create_history(&model).is_empty());
+
+        model.options.history = true;
+        let history = create_create_history(&model);
+        assert_eq!(5, history.len());
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS public.\"user_history\" (history_id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, \
+             entity_id bigint, operation text NOT NULL, changed_by text NOT NULL, changed_at timestamptz NOT NULL DEFAULT now(), \
+             old_row jsonb, new_row jsonb);",
+            history[0]
+        );
+        assert_eq!("CREATE INDEX IF NOT EXISTS user_history_entity_id_idx ON public.\"user_history\" (entity_id);", history[1]);
+        assert!(history[2].starts_with("CREATE OR REPLACE FUNCTION public.user_history_fn() RETURNS trigger AS $$ BEGIN"));
+        assert!(history[2].contains("CASE WHEN TG_OP = 'DELETE' THEN OLD.\"id\" ELSE NEW.\"id\" END"));
+        assert!(history[2].contains("CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE to_jsonb(NEW) - '{password}'::text[] END"));
+        assert_eq!("DROP TRIGGER IF EXISTS user_history_trigger ON public.\"user\";", history[3]);
+        assert_eq!(
+            "CREATE TRIGGER user_history_trigger AFTER INSERT OR UPDATE OR DELETE ON public.\"user\" FOR EACH ROW \
+             EXECUTE FUNCTION public.user_history_fn();",
+            history[4]
+        );
+        assert_eq!(
+            "SELECT history_id, operation, changed_by, changed_at, old_row, new_row FROM public.\"user_history\" \
+             WHERE entity_id = $1 ORDER BY history_id",
+            create_get_history(&model)
+        );
+    }
+
+    #[test]
+    fn test_tenant_scoped_entity_queries() {
+        let spec = json!({"Order": {"type": "object", "x-crudify-tenancy": "tenant", "x-crudify-soft-delete": true,
+            "x-crudify-upsert": true, "properties": {"id": {"type": "integer", "format": "int64"},
+            "status": {"type": "string"}}}});
+        let model = crate::json_converter::convert_to_internal_model(&spec).unwrap().remove(0);
+        let tenant = "\"tenant_id\" = nullif(current_setting('crudify.tenant', true), '')";
+
+        assert!(create_create_table(&model).contains("\"tenant_id\" text NOT NULL DEFAULT nullif(current_setting('crudify.tenant', true), '')"));
+        assert_eq!("INSERT INTO public.\"order\" (\"id\", \"status\") VALUES ($1, $2) RETURNING \"id\", \"status\", \"deleted_at\", \"tenant_id\"", create_create_entity(&model));
+        assert_eq!(Some(tenant.to_string()), get_tenancy_filter(&model));
+        assert_eq!(
+            format!("SELECT \"id\", \"status\", \"deleted_at\", \"tenant_id\" FROM public.\"order\" WHERE \"id\" = $1 AND {} AND deleted_at IS NULL", tenant),
+            create_get_entity(&model, false)
+        );
+        assert_eq!(format!("SELECT \"id\", \"status\", \"deleted_at\", \"tenant_id\" FROM public.\"order\" WHERE \"id\" = $1 AND {}", tenant), create_get_entity(&model, true));
+        assert_eq!(
+            format!("UPDATE public.\"order\" SET \"id\" = $1, \"status\" = $2 WHERE \"id\" = $1 AND {} AND deleted_at IS NULL RETURNING \"id\", \"status\", \"deleted_at\", \"tenant_id\"", tenant),
+            create_update_entity(&model)
+        );
+        assert_eq!(
+            format!("UPDATE public.\"order\" SET deleted_at = CURRENT_TIMESTAMP WHERE \"id\" = $1 AND {} AND deleted_at IS NULL RETURNING \"id\"", tenant),
+            create_delete_entity(&model)
+        );
+        assert!(create_upsert_entity(&model).contains("WHERE existing.\"tenant_id\" = EXCLUDED.\"tenant_id\" RETURNING"));
+        assert!(create_get_related(&model, "id").ends_with(&format!("AND {} AND deleted_at IS NULL", tenant)));
+
+        let mut model = model;
+        model.options.history = true;
+        assert!(create_get_history(&model).contains(
+            "WHERE entity_id = $1 AND coalesce(new_row, old_row) ->> 'tenant_id' = nullif(current_setting('crudify.tenant', true), '')"
+        ));
+    }
+
     #[test]
     fn test_delete_entity() {
         let props = indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String};
-        let expected = "DELETE FROM public.order WHERE id = ?";
+        let expected = "DELETE FROM public.\"order\" WHERE \"id\" = $1 RETURNING \"id\"";
         assert_eq!(
             expected,
             create_delete_entity(&InternalModel::new_with_props("Order".to_string(), props))
         );
     }
 
+    #[test]
+    fn test_update_condition() {
+        let model = model_with_extensions();
+        assert_eq!("\"id\" = $1 AND deleted_at IS NULL", get_update_condition(&model));
+        assert_eq!("\"id\" = $1", get_update_condition(&InternalModel::new("Order".to_string())));
+    }
+
+    #[test]
+    fn test_versioned_entity_queries() {
+        let mut model = model_with_extensions();
+        model.options.versioned = true;
+        let mut version = Property::new(RustDataType::I64);
+        version.read_only = true;
+        model.properties.as_mut().unwrap().insert("version".to_string(), version);
+
+        assert_eq!(
+            "SELECT \"id\", \"pet_id\", \"deleted_at\", \"version\" FROM store.\"orders\" WHERE \"id\" = $1 AND deleted_at IS NULL",
+            create_get_entity(&model, false)
+        );
+        assert_eq!(
+            "UPDATE store.\"orders\" SET \"id\" = $1, \"pet_id\" = $3, version = version + 1 \
+             WHERE \"id\" = $1 AND version = COALESCE($2, version) AND deleted_at IS NULL RETURNING \"id\", \"pet_id\", \"deleted_at\", \"version\"",
+            create_update_entity(&model)
+        );
+        assert_eq!(
+            "UPDATE store.\"orders\" SET deleted_at = CURRENT_TIMESTAMP, version = version + 1 \
+             WHERE \"id\" = $1 AND version = COALESCE($2, version) AND deleted_at IS NULL RETURNING \"id\"",
+            create_delete_entity(&model)
+        );
+        model.options.soft_delete = false;
+        assert_eq!(
+            "DELETE FROM store.\"orders\" WHERE \"id\" = $1 AND version = COALESCE($2, version) RETURNING \"id\"",
+            create_delete_entity(&model)
+        );
+    }
+
     #[test]
     fn test_update_entity_with_multiple_properties() {
         let props = indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String};
-        let expected = "UPDATE public.order SET id = $1, name = $2 WHERE id = $1";
+        let expected = "UPDATE public.\"order\" SET \"id\" = $1, \"name\" = $2 WHERE \"id\" = $1 RETURNING \"id\", \"name\"";
         assert_eq!(
             expected,
             create_update_entity(&InternalModel::new_with_props("Order".to_string(), props))
@@ -153,7 +1371,7 @@ mod tests {
     #[test]
     fn test_update_entity_with_one_properties() {
         let props = indexmap! {"id".to_string() => RustDataType::I64};
-        let expected = "UPDATE public.order SET id = $1 WHERE id = $1";
+        let expected = "UPDATE public.\"order\" SET \"id\" = $1 WHERE \"id\" = $1 RETURNING \"id\"";
         assert_eq!(
             expected,
             create_update_entity(&InternalModel::new_with_props("Order".to_string(), props))
@@ -163,27 +1381,165 @@ mod tests {
     #[test]
     fn test_create_entity_with_multiple_properties() {
         let props = indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String};
-        let expected = "INSERT INTO public.order (id, name) VALUES ($1, $2)";
+        let expected = "INSERT INTO public.\"order\" (\"id\", \"name\") VALUES ($1, $2) RETURNING \"id\", \"name\"";
         assert_eq!(
             expected,
             create_create_entity(&InternalModel::new_with_props("Order".to_string(), props))
         );
     }
 
+    fn searchable_article() -> InternalModel {
+        let mut model = InternalModel::new_with_props(
+            "Article".to_string(),
+            indexmap! {"id".to_string() => RustDataType::I64, "title".to_string() => RustDataType::String,
+            "body".to_string() => RustDataType::String},
+        );
+        let properties = model.properties.as_mut().unwrap();
+        properties.get_mut("title").unwrap().searchable = true;
+        properties.get_mut("body").unwrap().searchable = true;
+        properties.get_mut("body").unwrap().storage.column_name = Some("content".to_string());
+        model
+    }
+
+    #[test]
+    fn test_search_vector_and_index() {
+        let mut model = searchable_article();
+        assert_eq!(
+            "CREATE TABLE IF NOT EXISTS public.\"article\" (\"id\" bigint, \"title\" text, \"content\" text, search_vector tsvector GENERATED ALWAYS AS \
+             (to_tsvector('simple', coalesce(\"title\", '') || ' ' || coalesce(\"content\", ''))) STORED);",
+            create_create_table(&model)
+        );
+        assert_eq!(
+            vec!["CREATE INDEX IF NOT EXISTS article_search_vector_idx ON public.\"article\" USING GIN (search_vector);"],
+            create_create_indexes(&model)
+        );
+
+        model.options.history = true;
+        assert!(create_create_history(&model)[2].contains(" - '{search_vector}'::text[]"));
+        assert!(!is_searchable(&InternalModel::new("Article".to_string())));
+    }
+
+    #[test]
+    fn test_search_entities() {
+        let mut model = searchable_article();
+        model.options.search_config = Some("english".to_string());
+        model.options.soft_delete = true;
+        assert_eq!(
+            "SELECT \"id\", \"title\", \"content\", ts_rank(search_vector, search_query) AS search_rank, \
+             ts_headline('english', coalesce(\"title\", '') || ' ' || coalesce(\"content\", ''), search_query) AS search_snippet, \
+             count(*) OVER () AS search_total FROM public.\"article\", websearch_to_tsquery('english', $1) search_query \
+             WHERE search_vector @@ search_query AND deleted_at IS NULL ORDER BY search_rank DESC, \"id\" LIMIT $2 OFFSET $3",
+            create_search_entities(&model)
+        );
+    }
+
+    #[test]
+    fn test_upsert_entity() {
+        let mut model = model_with_extensions();
+        model.options.upsert_key = Some(vec!["pet_id".to_string()]);
+        model.indexes.push(Index {
+            name: None,
+            columns: vec!["pet_id".to_string()],
+            unique: true,
+            predicate: Some("deleted_at IS NULL".to_string()),
+        });
+        assert_eq!(
+            "INSERT INTO store.\"orders\" AS existing (\"id\", \"pet_id\") VALUES ($1, $2) ON CONFLICT (\"pet_id\") WHERE deleted_at IS NULL \
+             DO UPDATE SET \"id\" = EXCLUDED.\"id\", deleted_at = NULL RETURNING \"id\", \"pet_id\", \"deleted_at\", xmax = 0 AS inserted",
+            create_upsert_entity(&model)
+        );
+
+        model.options.soft_delete = false;
+        model.options.versioned = true;
+        model.indexes[0].predicate = None;
+        assert_eq!("(\"pet_id\")", get_upsert_conflict(&model));
+        assert_eq!(vec!["version = existing.version + 1"], get_upsert_assignments(&model));
+    }
+
+    #[test]
+    fn test_upsert_entity_with_only_the_key() {
+        let props = indexmap! {"id".to_string() => RustDataType::I64};
+        let mut model = InternalModel::new_with_props("Tag".to_string(), props);
+        model.options.upsert_key = Some(vec!["id".to_string()]);
+        assert_eq!(
+            "INSERT INTO public.\"tag\" AS existing (\"id\") VALUES ($1) ON CONFLICT (\"id\") DO UPDATE SET \"id\" = EXCLUDED.\"id\" \
+             RETURNING \"id\", xmax = 0 AS inserted",
+            create_upsert_entity(&model)
+        );
+    }
+
     #[test]
     fn test_create_entity_with_one_property() {
         let props = indexmap! {"id".to_string() => RustDataType::I64};
-        let expected = "INSERT INTO public.order (id) VALUES ($1)";
+        let expected = "INSERT INTO public.\"order\" (\"id\") VALUES ($1) RETURNING \"id\"";
         assert_eq!(
             expected,
             create_create_entity(&InternalModel::new_with_props("Order".to_string(), props))
         );
     }
 
+    #[test]
+    fn test_create_entity_with_read_only_and_write_only_properties() {
+        let mut model = read_only_id_and_password_model();
+        let expected = "INSERT INTO public.\"user\" (\"name\", \"password\") VALUES ($1, $2) RETURNING \"id\", \"name\"";
+        assert_eq!(expected, create_create_entity(&model));
+
+        model.properties.as_mut().unwrap().shift_remove("name");
+        model.properties.as_mut().unwrap().shift_remove("password");
+        let expected = "INSERT INTO public.\"user\" DEFAULT VALUES RETURNING \"id\"";
+        assert_eq!(expected, create_create_entity(&model));
+    }
+
+    #[test]
+    fn test_update_entity_with_read_only_and_write_only_properties() {
+        let expected = "UPDATE public.\"user\" SET \"name\" = $2, \"password\" = $3 WHERE \"id\" = $1 RETURNING \"id\", \"name\"";
+        assert_eq!(expected, create_update_entity(&read_only_id_and_password_model()));
+    }
+
+    #[test]
+    fn test_update_entity_with_id_not_first() {
+        let props = indexmap! {"name".to_string() => RustDataType::String, "id".to_string() => RustDataType::I64};
+        let expected = "UPDATE public.\"order\" SET \"name\" = $2, \"id\" = $1 WHERE \"id\" = $1 RETURNING \"name\", \"id\"";
+        assert_eq!(
+            expected,
+            create_update_entity(&InternalModel::new_with_props("Order".to_string(), props))
+        );
+    }
+
+    #[test]
+    fn test_create_table_with_generated_id() {
+        let expected = "CREATE TABLE IF NOT EXISTS public.\"user\" (\"id\" bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, \"name\" text, \"password\" text);";
+        assert_eq!(expected, create_create_table(&read_only_id_and_password_model()));
+    }
+
+    #[test]
+    fn test_create_sqlite_table() {
+        let mut model = read_only_id_and_password_model();
+        model.options.table_name = Some("store.user".to_string());
+        model.indexes = vec![Index { name: None, columns: vec!["name".to_string()], unique: true, predicate: None }];
+        assert!(!needs_postgres(&model));
+        assert_eq!(
+            vec![
+                "CREATE TABLE IF NOT EXISTS store.\"user\" (\"id\" integer PRIMARY KEY, \"name\" text, \"password\" text);",
+                "CREATE UNIQUE INDEX IF NOT EXISTS store.user_name_key ON \"user\" (\"name\");"
+            ],
+            create_sqlite_create_table(&model)
+        );
+
+        model.options.history = true;
+        assert!(needs_postgres(&model));
+    }
+
+    #[test]
+    fn test_get_entities_without_write_only_properties() {
+        let expected = "SELECT \"id\", \"name\" FROM public.\"user\"";
+        assert_eq!(expected, create_get_all_entities(&read_only_id_and_password_model()));
+    }
+
     #[test]
     fn test_get_entities_with_multiple_properties() {
         let props = indexmap! {"id".to_string() => RustDataType::I64, "name".to_string() => RustDataType::String};
-        let expected = "SELECT id, name FROM public.order";
+        let expected = "SELECT \"id\", \"name\" FROM public.\"order\"";
         assert_eq!(
             expected,
             create_get_all_entities(&InternalModel::new_with_props("Order".to_string(), props))
@@ -193,16 +1549,46 @@ mod tests {
     #[test]
     fn test_get_entities_with_property() {
         let props = indexmap! {"id".to_string() => RustDataType::I64};
-        let expected = "SELECT id FROM public.order";
+        let expected = "SELECT \"id\" FROM public.\"order\"";
         assert_eq!(
             expected,
             create_get_all_entities(&InternalModel::new_with_props("Order".to_string(), props))
         );
     }
 
+    #[test]
+    fn test_not_deleted_filter_without_soft_delete() {
+        assert_eq!(None, get_not_deleted_filter(&InternalModel::new("Order".to_string())));
+    }
+
+    #[test]
+    fn test_timestamped_entity_queries() {
+        let mut model = model_with_extensions();
+        model.options.timestamps = true;
+        assert_eq!(vec!["updated_at = CURRENT_TIMESTAMP"], get_update_assignments(&model));
+        assert_eq!(
+            "UPDATE store.\"orders\" SET \"id\" = $1, \"pet_id\" = $2, updated_at = CURRENT_TIMESTAMP WHERE \"id\" = $1 AND deleted_at IS NULL \
+             RETURNING \"id\", \"pet_id\", \"deleted_at\"",
+            create_update_entity(&model)
+        );
+        assert_eq!(
+            "UPDATE store.\"orders\" SET deleted_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE \"id\" = $1 AND deleted_at IS NULL RETURNING \"id\"",
+            create_delete_entity(&model)
+        );
+    }
+
+    #[test]
+    fn test_column_type() {
+        let mut property = Property::new(RustDataType::F64);
+        property.is_array = true;
+        assert_eq!("double precision", get_column_type(&property));
+        property.storage.sql_type = Some("numeric(10, 2)".to_string());
+        assert_eq!("numeric(10, 2)", get_column_type(&property));
+    }
+
     #[test]
     fn test_get_entities_without_properties() {
-        let expected = "SELECT * FROM public.order";
+        let expected = "SELECT * FROM public.\"order\"";
         assert_eq!(expected, create_get_all_entities(&InternalModel::new("Order".to_string())));
     }
 }
@@ -0,0 +1,592 @@
+use std::collections::BTreeSet;
+
+use serde_json::{json, Value};
+
+use crate::json_converter::RustDataType;
+use crate::main_file_creator::get_struct_properties;
+use crate::sql_creator::needs_postgres;
+use crate::{Endpoint, InternalModel, InternalModels, Property};
+
+/// `tests/api.rs` of the generated project: one test per model that creates, lists, gets, updates and deletes a row
+/// through the http api of the server, which the tests compile in. The payloads are made of the spec `example`s,
+/// required properties without one get a value that satisfies their constraints.
+pub fn create_api_tests(models: &InternalModels) -> String {
+    let tests: String = models.iter().map(get_model_test_code).collect();
+    format!(
+        "{}\n/// The api keys the tests authenticate with, they have every role of the api. `crudify-other` is a second \
+         tenant that must not see the rows of the first.\nconst CONFIG: &str = {:?};\n{}\n{}",
+        TEST_USAGES,
+        get_config(models),
+        TEST_SERVER,
+        tests
+    )
+}
+
+/// The `crudify.toml` of the test server, with the key of a second tenant if rows belong to one.
+fn get_config(models: &InternalModels) -> String {
+    let roles: BTreeSet<&String> = models
+        .iter()
+        .filter_map(|model| model.options.access.as_ref())
+        .flat_map(|access| {
+            [Endpoint::List, Endpoint::Create, Endpoint::Update, Endpoint::Delete]
+                .into_iter()
+                .flat_map(move |endpoint| access.roles(endpoint))
+        })
+        .collect();
+    let keys = if models.iter().any(|model| model.options.tenancy.is_some()) {
+        vec!["crudify-test", OTHER_TENANT]
+    } else {
+        vec!["crudify-test"]
+    };
+    keys.iter()
+        .map(|key| {
+            format!(
+                "[[auth.api_keys]]\nkey = \"{0}\"\nsubject = \"{0}\"\nroles = {1}\ntenant = \"{0}\"\n",
+                key,
+                json!(roles)
+            )
+        })
+        .collect()
+}
+
+/// The api key, subject and tenant of the principal the isolation of tenant and owner models is tested with.
+const OTHER_TENANT: &str = "crudify-other";
+
+fn get_model_test_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let properties: Vec<(&String, &Property)> = model.properties.iter().flatten().collect();
+    let has_id = properties.iter().any(|(key, _)| *key == "id");
+    let versioned = model.options.versioned;
+    let mut code = "".to_string();
+
+    if model.has_endpoint(Endpoint::Create) {
+        code.push_str(&format!(
+            r#"
+    let payload = {0}_payload();
+    let res = server.send(Method::POST, "/api/{0}", None, Some(&payload)).await;
+    assert_eq!(StatusCode::CREATED, res.status, "{{}}", res.body);
+"#,
+            name
+        ));
+        let (_, writable, _) = get_struct_properties(model);
+        let sent: Vec<_> = writable.into_iter().filter(|(_, property)| is_in_payload(property)).collect();
+        code.push_str(&get_compare_code(model, "payload", &sent));
+    } else if model.has_endpoint(Endpoint::List) {
+        code.push_str(&format!(
+            r#"
+    let res = server.send(Method::GET, "/api/{0}", None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+    assert!(res.body["items"].is_array());
+"#,
+            name
+        ));
+    }
+    if code.is_empty() {
+        return code;
+    }
+
+    if model.has_endpoint(Endpoint::Create) && !has_id {
+        if model.has_endpoint(Endpoint::List) {
+            code.push_str(&format!(
+                r#"
+    let res = server.send(Method::GET, "/api/{0}", None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+    assert!(res.body["items"].is_array());
+"#,
+                name
+            ));
+        }
+    } else if model.has_endpoint(Endpoint::Create) {
+        code.push_str(&format!(
+            "\n    let id = res.body[\"id\"].clone();\n    let path = format!(\"/api/{}/{{}}\", id_param(&id));\n",
+            name
+        ));
+        let mut if_match = "None";
+        if model.has_endpoint(Endpoint::List) {
+            code.push_str(&format!(
+                r#"
+    let res = server.send(Method::GET, &format!("/api/{0}?id={{}}", id_param(&id)), None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+    assert_eq!(json!([id]), json!(res.body["items"].as_array().unwrap().iter().map(|item| &item["id"]).collect::<Vec<_>>()));
+
+    let res = server.send(Method::GET, &path, None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+    assert_eq!(id, res.body["id"]);
+"#,
+                name
+            ));
+            if versioned {
+                code.push_str("    let etag = res.etag();\n");
+                if_match = "Some(&etag)";
+            }
+            if model.options.tenancy.is_some() {
+                code.push_str(&format!(
+                    r#"
+    let res = server.send_as({1:?}, Method::GET, &format!("/api/{0}?id={{}}", id_param(&id)), None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+    assert_eq!(json!([]), res.body["items"]);
+    let res = server.send_as({1:?}, Method::GET, &path, None, None).await;
+    assert_eq!(StatusCode::NOT_FOUND, res.status, "{{}}", res.body);
+"#,
+                    name, OTHER_TENANT
+                ));
+            }
+            if model.options.history && model.options.access.is_some() {
+                code.push_str(
+                    r#"
+    let res = server.send(Method::GET, &format!("{}/history", path), None, None).await;
+    assert_eq!(StatusCode::OK, res.status, "{}", res.body);
+    assert_eq!(json!("crudify-test"), res.body[0]["changed_by"]);
+"#,
+                );
+            }
+        } else if versioned {
+            // without a read the version of the new row is the one it was created with
+            code.push_str("    let etag = res.etag();\n");
+            if_match = "Some(&etag)";
+        }
+
+        let (_, _, updatable) = get_struct_properties(model);
+        if let Some((key, property)) = updatable.first().filter(|_| model.has_endpoint(Endpoint::Update)) {
+            let value = if is_in_payload(property) {
+                format!("payload[\"{}\"].clone()", key)
+            } else {
+                get_value_code(model, key, property)
+            };
+            code.push_str(&format!("\n    let patch = json!({{\"{}\": {}}});\n", key, value));
+            if model.options.tenancy.is_some() {
+                code.push_str(&get_other_tenant_code("PATCH", if_match, "Some(&patch)"));
+            }
+            code.push_str(&format!(
+                r#"    let res = server.send(Method::PATCH, &path, {}, Some(&patch)).await;
+    assert_eq!(StatusCode::OK, res.status, "{{}}", res.body);
+"#,
+                if_match
+            ));
+            code.push_str(&get_compare_code(model, "patch", &[(*key, *property)]));
+            if versioned {
+                code.push_str("    let etag = res.etag();\n");
+            }
+        }
+
+        if model.has_endpoint(Endpoint::Delete) {
+            if model.options.tenancy.is_some() {
+                code.push('\n');
+                code.push_str(&get_other_tenant_code("DELETE", if_match, "None"));
+            }
+            code.push_str(&format!(
+                r#"
+    let res = server.send(Method::DELETE, &path, {}, None).await;
+    assert_eq!(StatusCode::NO_CONTENT, res.status, "{{}}", res.body);
+"#,
+                if_match
+            ));
+            if model.has_endpoint(Endpoint::List) {
+                code.push_str(
+                    r#"    let res = server.send(Method::GET, &path, None, None).await;
+    assert_eq!(StatusCode::NOT_FOUND, res.status, "{}", res.body);
+"#,
+                );
+            }
+        }
+    }
+
+    let payload = if model.has_endpoint(Endpoint::Create) { get_payload_fn_code(model) } else { "".to_string() };
+    let ignore = if needs_postgres(model) {
+        "#[cfg_attr(not(feature = \"postgres-tests\"), ignore = \"needs Postgres, run with --features postgres-tests\")]\n"
+    } else {
+        ""
+    };
+    format!(
+        "{}\n#[tokio::test]\n{}async fn {}_crud() {{\n    let server = Server::start().await;\n{}}}\n",
+        payload, ignore, name, code
+    )
+}
+
+/// The other tenant can't change the row at `path`, it is missing for them.
+fn get_other_tenant_code(method: &str, if_match: &str, body: &str) -> String {
+    format!(
+        r#"    let res = server.send_as({:?}, Method::{}, &path, {}, {}).await;
+    assert_eq!(StatusCode::NOT_FOUND, res.status, "{{}}", res.body);
+"#,
+        OTHER_TENANT, method, if_match, body
+    )
+}
+
+/// Properties the create payload holds, required ones unless the server fills them in and all with an example.
+fn is_in_payload(property: &Property) -> bool {
+    property.example.is_some() || (property.required && !property.has_default())
+}
+
+fn get_payload_fn_code(model: &InternalModel) -> String {
+    let (_, writable, _) = get_struct_properties(model);
+    let fields: Vec<String> = writable
+        .iter()
+        .filter(|(key, property)| is_in_payload(property) || *key == "id")
+        .map(|(key, property)| format!("        \"{}\": {},\n", key, get_value_code(model, key, property)))
+        .collect();
+    format!(
+        "\nfn {}_payload() -> Value {{\n    json!({{\n{}    }})\n}}\n",
+        model.name.to_lowercase(),
+        fields.concat()
+    )
+}
+
+/// Asserts that `res.body` holds the `sent` values of the readable properties whose json the server sends back
+/// unchanged, dates and floats may be formatted differently.
+fn get_compare_code(model: &InternalModel, sent: &str, properties: &[(&String, &Property)]) -> String {
+    let keys: Vec<String> = properties
+        .iter()
+        .filter(|(key, property)| {
+            property.is_readable()
+                && !is_unique(model, key, property)
+                && matches!(
+                    property.data_type,
+                    RustDataType::U8 | RustDataType::I32 | RustDataType::I64 | RustDataType::String | RustDataType::Bool
+                )
+        })
+        .map(|(key, _)| format!("\"{}\"", key))
+        .collect();
+    match keys.len() {
+        0 => "".to_string(),
+        1 => format!("    assert_eq!({0}[{1}], res.body[{1}]);\n", sent, keys[0]),
+        _ => format!(
+            "    for key in [{}] {{\n        assert_eq!({}[key], res.body[key], \"{{}}\", key);\n    }}\n",
+            keys.join(", "),
+            sent
+        ),
+    }
+}
+
+/// A writable `id` and the columns of unique indexes get a value no earlier run has used, the others the same one.
+fn is_unique(model: &InternalModel, key: &str, property: &Property) -> bool {
+    let column = property.column_name(key);
+    key == "id"
+        || model.indexes.iter().any(|index| index.unique && index.columns.iter().any(|c| c == column))
+        || model.options.upsert_key.iter().flatten().any(|c| c == column)
+}
+
+/// The json of a valid value for the property, or a rust expression making a unique one.
+fn get_value_code(model: &InternalModel, key: &str, property: &Property) -> String {
+    if is_unique(model, key, property) && property.enum_values.is_none() && !property.is_array {
+        match property.data_type {
+            RustDataType::I32 | RustDataType::I64 => return "unique_id()".to_string(),
+            RustDataType::String if property.format.as_deref() == Some("uuid") => {
+                return "uuid::Uuid::new_v4().to_string()".to_string();
+            }
+            RustDataType::String if property.format.is_none() => {
+                let len = property.constraints.max_length.unwrap_or(32).min(32);
+                return format!("unique_string({})", len);
+            }
+            _ => {}
+        }
+    }
+    get_value(property).to_string()
+}
+
+fn get_value(property: &Property) -> Value {
+    if let Some(example) = &property.example {
+        return match example {
+            Value::Array(_) => example.clone(),
+            example if property.is_array => json!([example]),
+            example => example.clone(),
+        };
+    }
+    let value = match &property.enum_values {
+        Some(values) if !values.is_empty() => values[0].clone(),
+        _ => get_synthesized_value(property),
+    };
+    if property.is_array {
+        let items = property.constraints.min_items.unwrap_or(1).max(1) as usize;
+        json!(vec![value; items])
+    } else {
+        value
+    }
+}
+
+fn get_synthesized_value(property: &Property) -> Value {
+    let constraints = &property.constraints;
+    let number = match (constraints.minimum, constraints.maximum) {
+        (Some(minimum), Some(maximum)) => (minimum + maximum) / 2.0,
+        (Some(minimum), None) => minimum + 1.0,
+        (None, Some(maximum)) => maximum - 1.0,
+        (None, None) => 1.5,
+    };
+    match property.data_type {
+        RustDataType::U8 | RustDataType::I32 | RustDataType::I64 => {
+            let mut integer = number.round();
+            if let Some(minimum) = constraints.minimum {
+                integer = integer.max(if constraints.exclusive_minimum { minimum.floor() + 1.0 } else { minimum.ceil() });
+            }
+            if let Some(maximum) = constraints.maximum {
+                integer = integer.min(if constraints.exclusive_maximum { maximum.ceil() - 1.0 } else { maximum.floor() });
+            }
+            json!(integer as i64)
+        }
+        RustDataType::F32 | RustDataType::F64 => json!(number),
+        RustDataType::Bool => json!(true),
+        RustDataType::Date => json!("2024-01-01"),
+        RustDataType::DateTime => json!("2024-01-01T00:00:00Z"),
+        RustDataType::String => match property.format.as_deref() {
+            Some("email") => json!("test@example.com"),
+            Some("uuid") => json!("00000000-0000-4000-8000-000000000000"),
+            Some("uri") | Some("url") => json!("https://example.com"),
+            _ => {
+                let len = constraints.min_length.unwrap_or(0).max(4).min(constraints.max_length.unwrap_or(u64::MAX));
+                json!("test".chars().chain(std::iter::repeat('x')).take(len as usize).collect::<String>())
+            }
+        },
+    }
+}
+
+const TEST_USAGES: &str = r#"//! Creates, lists, gets, updates and deletes a row of every model through the api of the server, which runs in the
+//! test process on an in-memory SQLite database, so `cargo test` needs nothing else. The tests of models using
+//! features of Postgres are ignored unless they run with `--features postgres-tests`, which runs all of them on the
+//! Postgres database of the `PG_*` variables like the server itself, i.e.
+//! `PG_HOST=localhost cargo test --features postgres-tests`.
+
+// the helpers are shared by all models, not every api uses all of them
+#![allow(dead_code)]
+
+#[path = "../src/main.rs"]
+mod server;
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::{client::HttpConnector, header, Body, Client, HeaderMap, Method, Request, StatusCode};
+use serde_json::{json, Value};
+"#;
+
+const TEST_SERVER: &str = r#"
+/// Every test starts its own server, one at a time as they configure it through the environment and the servers of a
+/// Postgres database must not create the tables at the same time.
+static SERVER_LOCK: Mutex<()> = Mutex::new(());
+
+struct Server {
+    task: tokio::task::JoinHandle<Result<(), server::AppError>>,
+    addr: String,
+    client: Client<HttpConnector>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+}
+
+impl Response {
+    fn etag(&self) -> String {
+        self.headers
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .expect("A versioned row has an ETag")
+            .to_string()
+    }
+}
+
+impl Server {
+    /// Starts the server on a free port, it panics if the server doesn't come up, i.e. without a Postgres database.
+    async fn start() -> Server {
+        let lock = SERVER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Expected a free port")
+            .port();
+        let path = std::env::temp_dir().join(format!("crudify-test-{}.toml", port));
+        std::fs::write(&path, CONFIG).expect("Could not write the config of the test server");
+        std::env::set_var("CRUDIFY_CONFIG", &path);
+        std::env::set_var("SERVER_ADDR", format!("127.0.0.1:{}", port));
+        std::env::set_var("GRPC_ADDR", "");
+        let config = server::Config::load();
+        let _ = std::fs::remove_file(&path);
+        let config = config.unwrap_or_else(|e| panic!("{}", e));
+        let server = Server {
+            task: tokio::spawn(server::serve(config)),
+            addr: format!("http://127.0.0.1:{}", port),
+            client: Client::new(),
+            _lock: lock,
+        };
+
+        for _ in 0..100 {
+            if server.send(Method::GET, "/api/openapi.json", None, None).await.status == StatusCode::OK {
+                return server;
+            }
+            if server.task.is_finished() {
+                panic!("The server on {} stopped, see its error above", server.addr);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("The server did not start on {}", server.addr);
+    }
+
+/// Sends the request with the api key of the tests, the status is `599` if the server didn't answer.
+    async fn send(&self, method: Method, path: &str, if_match: Option<&str>, body: Option<&Value>) -> Response {
+        self.send_as("crudify-test", method, path, if_match, body).await
+    }
+
+    async fn send_as(&self, api_key: &str, method: Method, path: &str, if_match: Option<&str>, body: Option<&Value>) -> Response {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.addr, path))
+            .header("X-Api-Key", api_key);
+        if let Some(if_match) = if_match {
+            request = request.header(header::IF_MATCH, if_match);
+        }
+        let request = match body {
+            Some(body) => request.header(header::CONTENT_TYPE, "application/json").body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let res = match self.client.request(request.expect("Expected a valid request")).await {
+            Ok(res) => res,
+            Err(e) => return Response { status: StatusCode::from_u16(599).unwrap(), headers: HeaderMap::new(), body: json!(e.to_string()) },
+        };
+        let status = res.status();
+        let headers = res.headers().clone();
+        let bytes = hyper::body::to_bytes(res.into_body()).await.expect("Could not read the body");
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap_or_else(|_| json!(String::from_utf8_lossy(&bytes)))
+        };
+        Response { status, headers, body }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A number no earlier run of the tests has used, for ids and unique columns.
+fn unique_id() -> i64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).expect("Expected a time after 1970").as_nanos();
+    (nanos % i32::MAX as u128) as i64
+}
+
+fn unique_string(len: usize) -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..len].to_string()
+}
+
+/// The id as it appears in a path or a query.
+fn id_param(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.to_string(),
+        id => id.to_string(),
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn models(spec: Value) -> InternalModels {
+        crate::json_converter::convert_to_internal_model(&spec).unwrap()
+    }
+
+    #[test]
+    fn test_payload_from_examples_and_constraints() {
+        let models = models(json!({"Pet": {"type": "object", "required": ["name", "age", "status", "code"],
+            "x-crudify-indexes": [{"columns": ["code"], "unique": true}],
+            "properties": {
+                "id": {"type": "integer", "format": "int64", "readOnly": true},
+                "name": {"type": "string", "example": "doggie"},
+                "age": {"type": "integer", "format": "int32", "minimum": 3, "maximum": 10},
+                "status": {"type": "string", "enum": ["available", "sold"]},
+                "code": {"type": "string", "maxLength": 8},
+                "nickname": {"type": "string", "minLength": 6},
+                "born": {"type": "string", "format": "date", "example": "2020-02-02"}}}}));
+
+        let code = create_api_tests(&models);
+        assert!(code.contains("#[path = \"../src/main.rs\"]\nmod server;"));
+        assert!(code.contains(
+            "fn pet_payload() -> Value {\n    json!({\n        \"age\": 7,\n        \"born\": \"2020-02-02\",\n        \
+             \"code\": unique_string(8),\n        \"name\": \"doggie\",\n        \"status\": \"available\",\n    })\n}"
+        ));
+        assert!(!code.contains("\"nickname\""));
+        assert!(code.contains("for key in [\"age\", \"name\", \"status\"] {\n        assert_eq!(payload[key], res.body[key]"));
+    }
+
+    #[test]
+    fn test_crud_steps() {
+        let models = models(json!({"Order": {"type": "object", "x-crudify-versioned": true, "x-crudify-roles": {"delete": ["admin"]},
+            "properties": {"id": {"type": "integer", "format": "int64", "readOnly": true}, "status": {"type": "string"}}}}));
+
+        let code = create_api_tests(&models);
+        assert!(code.contains("async fn order_crud() {\n    let server = Server::start().await;\n"));
+        assert!(!code.contains("PG_HOST is not set"));
+        assert!(code.contains("server.send(Method::POST, \"/api/order\", None, Some(&payload)).await;"));
+        assert!(code.contains("server.send(Method::GET, &format!(\"/api/order?id={}\", id_param(&id)), None, None).await;"));
+        assert!(code.contains("let etag = res.etag();\n\n    let patch = json!({\"status\": \"test\"});"));
+        assert!(code.contains("server.send(Method::PATCH, &path, Some(&etag), Some(&patch)).await;"));
+        assert!(code.contains("server.send(Method::DELETE, &path, Some(&etag), None).await;"));
+        assert!(code.contains("assert_eq!(StatusCode::NOT_FOUND, res.status"));
+        assert!(code.contains("roles = [\\\"admin\\\"]"));
+    }
+
+    #[test]
+    fn test_history_records_the_test_principal() {
+        let spec = json!({"Order": {"type": "object", "x-crudify-history": true, "x-crudify-roles": {"delete": ["admin"]},
+            "properties": {"id": {"type": "integer", "format": "int64", "readOnly": true}, "status": {"type": "string"}}}});
+
+        let code = create_api_tests(&models(spec.clone()));
+        assert!(code.contains("server.send(Method::GET, &format!(\"{}/history\", path), None, None).await;"));
+        assert!(code.contains("assert_eq!(json!(\"crudify-test\"), res.body[0][\"changed_by\"]);"));
+
+        let mut spec = spec;
+        spec["Order"].as_object_mut().unwrap().remove("x-crudify-roles");
+        assert!(!create_api_tests(&models(spec)).contains("/history"));
+    }
+
+    #[test]
+    fn test_other_tenant_sees_nothing() {
+        let spec = json!({"Order": {"type": "object", "x-crudify-tenancy": "tenant", "properties": {
+            "id": {"type": "integer", "format": "int64", "readOnly": true}, "status": {"type": "string"}}}});
+
+        let code = create_api_tests(&models(spec.clone()));
+        assert!(code.contains("#[cfg_attr(not(feature = \"postgres-tests\"), ignore = \"needs Postgres, run with --features postgres-tests\")]\nasync fn order_crud()"));
+        assert!(code.contains("key = \\\"crudify-other\\\"\\nsubject = \\\"crudify-other\\\"\\nroles = []\\ntenant = \\\"crudify-other\\\"\\n"));
+        assert!(code.contains(
+            "server.send_as(\"crudify-other\", Method::GET, &format!(\"/api/order?id={}\", id_param(&id)), None, None).await;\n    \
+             assert_eq!(StatusCode::OK, res.status, \"{}\", res.body);\n    assert_eq!(json!([]), res.body[\"items\"]);"
+        ));
+        assert!(code.contains("server.send_as(\"crudify-other\", Method::GET, &path, None, None).await;\n    assert_eq!(StatusCode::NOT_FOUND"));
+        assert!(code.contains(
+            "server.send_as(\"crudify-other\", Method::PATCH, &path, None, Some(&patch)).await;\n    assert_eq!(StatusCode::NOT_FOUND, res.status, \"{}\", res.body);\n    \
+             let res = server.send(Method::PATCH, &path, None, Some(&patch)).await;"
+        ));
+        assert!(code.contains(
+            "server.send_as(\"crudify-other\", Method::DELETE, &path, None, None).await;\n    assert_eq!(StatusCode::NOT_FOUND, res.status, \"{}\", res.body);\n\n    \
+             let res = server.send(Method::DELETE, &path, None, None).await;"
+        ));
+
+        let mut spec = spec;
+        spec["Order"].as_object_mut().unwrap().remove("x-crudify-tenancy");
+        let code = create_api_tests(&models(spec));
+        assert!(!code.contains("send_as(\"crudify-other\"") && !code.contains("key = \\\"crudify-other"));
+        assert!(!code.contains("ignore = "));
+    }
+
+    #[test]
+    fn test_skipped_endpoints() {
+        let models = models(json!({
+            "Tag": {"type": "object", "required": ["name"], "properties": {"name": {"type": "string"}}},
+            "Log": {"type": "object", "x-crudify-skip-endpoints": ["create"], "properties": {
+                "id": {"type": "integer", "format": "int64", "readOnly": true}}}}));
+
+        let code = create_api_tests(&models);
+        let log = &code[code.find("async fn log_crud").unwrap()..code.find("async fn tag_crud").unwrap()];
+        assert!(log.contains("server.send(Method::GET, \"/api/log\", None, None).await;"));
+        assert!(!log.contains("Method::POST"));
+        let tag = &code[code.find("async fn tag_crud").unwrap()..];
+        assert!(tag.contains("server.send(Method::POST, \"/api/tag\", None, Some(&payload)).await;"));
+        assert!(tag.contains("assert!(res.body[\"items\"].is_array());"));
+        assert!(!tag.contains("let id ="));
+        assert!(!code.contains("fn log_payload"));
+    }
+}
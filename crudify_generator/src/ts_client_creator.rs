@@ -0,0 +1,441 @@
+use crate::json_converter::RustDataType;
+use crate::main_file_creator::{get_rust_type, get_struct_properties, has_upsert};
+use crate::sql_creator::is_searchable;
+use crate::{Endpoint, InternalModel, InternalModels, Property};
+
+/// `models.ts` with an interface per struct of `get_structs` and the names of the fields a list can be filtered and
+/// sorted by.
+pub fn create_ts_models(models: &InternalModels) -> String {
+    let mut code = TS_TYPES.trim_start().to_string();
+    for model in models {
+        let (readable, writable, updatable) = get_struct_properties(model);
+        code.push_str(&get_interface_code(model, &model.name, &readable, false));
+        if model.has_endpoint(Endpoint::Create) {
+            code.push_str(&get_interface_code(model, &format!("Create{}", model.name), &writable, true));
+        }
+        if model.has_endpoint(Endpoint::Update) {
+            code.push_str(&get_interface_code(model, &format!("Update{}", model.name), &updatable, true));
+        }
+        let fields: Vec<_> = readable
+            .iter()
+            .filter(|(_, property)| !property.is_array)
+            .map(|(key, _)| format!("{:?}", key))
+            .collect();
+        code.push_str(&format!(
+            "\nexport type {}Field = {};\n",
+            model.name,
+            if fields.is_empty() { "never".to_string() } else { fields.join(" | ") }
+        ));
+    }
+    code
+}
+
+/// `client.ts`, a `fetch` based client with one method per route like the rust client.
+pub fn create_ts_client(models: &InternalModels) -> String {
+    let mut imports = vec!["AppError".to_string(), "FilterValue".to_string(), "ListQuery".to_string()];
+    let is_used = |used: &dyn Fn(&InternalModel) -> bool| models.iter().any(|model| model.has_endpoint(Endpoint::List) && used(model));
+    if is_used(&|_| true) {
+        imports.push("Page".to_string());
+    }
+    if is_used(&is_searchable) {
+        imports.push("SearchHit".to_string());
+    }
+    if is_used(&|model| model.options.history) {
+        imports.push("HistoryEntry".to_string());
+    }
+    for model in models {
+        if [Endpoint::List, Endpoint::Create, Endpoint::Update].iter().any(|endpoint| model.has_endpoint(*endpoint)) {
+            imports.push(model.name.to_string());
+        }
+        if model.has_endpoint(Endpoint::List) {
+            imports.push(format!("{}Field", model.name));
+        }
+        if model.has_endpoint(Endpoint::Create) {
+            imports.push(format!("Create{}", model.name));
+        }
+        if model.has_endpoint(Endpoint::Update) {
+            imports.push(format!("Update{}", model.name));
+        }
+    }
+    let methods: String = models.iter().map(get_ts_methods_code).collect();
+
+    format!(
+        "// Generated by crudify, changes are overwritten.\n\nimport type {{ {} }} from './models';\n{}{}}}\n",
+        imports.join(", "),
+        TS_CLIENT,
+        methods
+    )
+}
+
+/// Properties the generated struct holds as `Option` may be `null`, request properties with a default may be left out.
+fn get_interface_code(model: &InternalModel, name: &str, properties: &[(&String, &Property)], is_request: bool) -> String {
+    let fields: String = properties
+        .iter()
+        .map(|(key, property)| {
+            let nullable = get_rust_type(property).starts_with("Option<");
+            let optional = is_request && (nullable || property.has_default());
+            let mut field = "".to_string();
+            if let Some(description) = &property.description {
+                field.push_str(&format!("  /** {} */\n", description.replace("*/", "*\\/")));
+            }
+            field.push_str(&format!(
+                "  {}{}: {}{};\n",
+                get_ts_key(key),
+                if optional { "?" } else { "" },
+                get_ts_type(property),
+                if nullable { " | null" } else { "" }
+            ));
+            field
+        })
+        .collect();
+    let doc = match &model.description {
+        Some(description) if !is_request => format!("\n/** {} */", description.replace("*/", "*\\/")),
+        _ => "".to_string(),
+    };
+
+    format!("{}\nexport interface {} {{\n{}}}\n", doc, name, fields)
+}
+
+/// Keys that aren't identifiers are quoted.
+fn get_ts_key(key: &str) -> String {
+    let is_identifier = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key.to_string()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+/// Enums become unions of their values. Dates are sent as ISO 8601 strings and 64 bit integers as numbers.
+fn get_ts_type(property: &Property) -> String {
+    let ts_type = match (&property.enum_values, &property.data_type) {
+        (Some(values), _) if !values.is_empty() => values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" | "),
+        (_, RustDataType::U8 | RustDataType::I32 | RustDataType::I64 | RustDataType::F32 | RustDataType::F64) => "number".to_string(),
+        (_, RustDataType::String | RustDataType::Date | RustDataType::DateTime) => "string".to_string(),
+        (_, RustDataType::Bool) => "boolean".to_string(),
+    };
+    match (property.is_array, ts_type.contains(" | ")) {
+        (true, true) => format!("({})[]", ts_type),
+        (true, false) => format!("{}[]", ts_type),
+        (false, _) => ts_type,
+    }
+}
+
+fn get_ts_id_type(model: &InternalModel) -> &'static str {
+    match model.properties.as_ref().and_then(|properties| properties.get("id")) {
+        Some(id) if id.data_type == RustDataType::String => "string",
+        _ => "number",
+    }
+}
+
+fn get_ts_methods_code(model: &InternalModel) -> String {
+    let name = model.name.to_lowercase();
+    let model_name = &model.name;
+    let id = get_ts_id_type(model);
+    let (version_param, version_arg) = if model.options.versioned {
+        ("version: number, ", ", version")
+    } else {
+        ("", "")
+    };
+    let mut code = "".to_string();
+
+    if model.has_endpoint(Endpoint::List) {
+        code.push_str(&format!(
+            r#"
+  /** `GET /api/{name}`, a page of the rows matching the `query`. */
+  list{model_name}(query: ListQuery<{model_name}Field> = {{}}): Promise<Page<{model_name}>> {{
+    return this.request('GET', ['{name}'], {{ query: listParams(query) }});
+  }}
+
+  /** `GET /api/{name}/:id` */
+  get{model_name}(id: {id}): Promise<{model_name}> {{
+    return this.request('GET', ['{name}', id]);
+  }}
+"#
+        ));
+        if is_searchable(model) {
+            code.push_str(&format!(
+                r#"
+  /** `GET /api/{name}/search`, the best ranked rows for `q`. */
+  search{model_name}(q: string, page: {{ limit?: number; offset?: number }} = {{}}): Promise<Page<SearchHit<{model_name}>>> {{
+    const query = new URLSearchParams({{ q }});
+    if (page.limit !== undefined) query.append('limit', String(page.limit));
+    if (page.offset !== undefined) query.append('offset', String(page.offset));
+    return this.request('GET', ['{name}', 'search'], {{ query }});
+  }}
+"#
+            ));
+        }
+        if model.options.history {
+            code.push_str(&format!(
+                r#"
+  /** `GET /api/{name}/:id/history`, the changes of the row, oldest first. */
+  get{model_name}History(id: {id}): Promise<HistoryEntry[]> {{
+    return this.request('GET', ['{name}', id, 'history']);
+  }}
+"#
+            ));
+        }
+    }
+
+    if model.has_endpoint(Endpoint::Create) {
+        code.push_str(&format!(
+            r#"
+  /** `POST /api/{name}` */
+  create{model_name}({name}: Create{model_name}): Promise<{model_name}> {{
+    return this.request('POST', ['{name}'], {{ body: {name} }});
+  }}
+"#
+        ));
+    }
+    if has_upsert(model) {
+        code.push_str(&format!(
+            r#"
+  /** `PUT /api/{name}`, creates the row or updates the one with the same upsert key. */
+  upsert{model_name}({name}: Create{model_name}): Promise<{model_name}> {{
+    return this.request('PUT', ['{name}'], {{ body: {name} }});
+  }}
+"#
+        ));
+    }
+
+    if model.has_endpoint(Endpoint::Update) {
+        code.push_str(&format!(
+            r#"
+  /** `PUT /api/{name}/:id` */
+  update{model_name}(id: {id}, {version_param}{name}: Update{model_name}): Promise<{model_name}> {{
+    return this.request('PUT', ['{name}', id], {{ body: {name}{version_arg} }});
+  }}
+
+  /** `PATCH /api/{name}/:id`, changes the given properties only. */
+  patch{model_name}(id: {id}, {version_param}patch: Partial<Update{model_name}>): Promise<{model_name}> {{
+    return this.request('PATCH', ['{name}', id], {{ body: patch{version_arg} }});
+  }}
+"#
+        ));
+    }
+
+    if model.has_endpoint(Endpoint::Delete) {
+        code.push_str(&format!(
+            r#"
+  /** `DELETE /api/{name}/:id` */
+  delete{model_name}(id: {id}{version}): Promise<void> {{
+    return this.request('DELETE', ['{name}', id]{options});
+  }}
+"#,
+            version = if model.options.versioned { ", version: number" } else { "" },
+            options = if model.options.versioned { ", { version }" } else { "" }
+        ));
+    }
+
+    code
+}
+
+/// The types every api shares, written in front of the model interfaces.
+const TS_TYPES: &str = r#"
+// Generated by crudify, changes are overwritten.
+
+/** The body of every error answer of the server. */
+export interface AppError {
+  status_code: number;
+  errors: string[];
+}
+
+export interface Page<T> {
+  items: T[];
+  total: number;
+  /** Pass as `cursor` to get the next page. */
+  next_cursor: string | null;
+}
+
+export interface SearchHit<T> {
+  item: T;
+  rank: number;
+  snippet: string;
+}
+
+/** A change of a row, the rows are the whole table rows by column. */
+export interface HistoryEntry {
+  history_id: number;
+  operation: string;
+  changed_by: string;
+  changed_at: string;
+  old_row: Record<string, unknown> | null;
+  new_row: Record<string, unknown> | null;
+}
+
+export type FilterValue = string | number | boolean | Array<string | number>;
+
+/** Filters, sorting and paging of a list request. `in` filters take an array. */
+export interface ListQuery<F extends string> {
+  limit?: number;
+  offset?: number;
+  cursor?: string;
+  /** Fields to sort by, descending with `-field`. */
+  sort?: Array<F | `-${F}`>;
+  /** Relations to embed into the rows. */
+  include?: string[];
+  includeDeleted?: boolean;
+  filters?: Partial<Record<F | `${F}[${'lt' | 'gt' | 'in' | 'like'}]`, FilterValue>>;
+}
+"#;
+
+/// The part of the client that doesn't depend on the models, the methods follow.
+const TS_CLIENT: &str = r#"
+/** The server answered with an error status. */
+export class ApiError extends Error {
+  constructor(readonly status: number, readonly errors: string[]) {
+    super(`${status} ${errors.join(', ')}`);
+    this.name = 'ApiError';
+  }
+}
+
+export interface ClientOptions {
+  bearerToken?: string;
+  apiKey?: string;
+  /** Defaults to the global `fetch`. */
+  fetch?: typeof fetch;
+}
+
+interface RequestOptions {
+  query?: URLSearchParams;
+  body?: unknown;
+  /** Sent as `If-Match` to change a versioned row. */
+  version?: number;
+}
+
+/** The `errors` of an `AppError` body, or the body itself. */
+function errorsOf(body: string): string[] {
+  try {
+    const error = JSON.parse(body) as Partial<AppError> | null;
+    if (Array.isArray(error?.errors)) return error.errors;
+  } catch {
+    // not an AppError
+  }
+  return [body];
+}
+
+function listParams<F extends string>(query: ListQuery<F>): URLSearchParams {
+  const params = new URLSearchParams();
+  if (query.limit !== undefined) params.append('limit', String(query.limit));
+  if (query.offset !== undefined) params.append('offset', String(query.offset));
+  if (query.cursor !== undefined) params.append('cursor', query.cursor);
+  if (query.sort?.length) params.append('sort', query.sort.join(','));
+  if (query.include?.length) params.append('include', query.include.join(','));
+  if (query.includeDeleted) params.append('include_deleted', 'true');
+  for (const [key, value] of Object.entries(query.filters ?? {}) as Array<[string, FilterValue | undefined]>) {
+    if (value !== undefined) params.append(key, Array.isArray(value) ? value.join(',') : String(value));
+  }
+  return params;
+}
+
+export class Client {
+  private readonly baseUrl: string;
+
+  /** A client for the server at `baseUrl`, i.e. `http://127.0.0.1:8000`. */
+  constructor(baseUrl: string, private readonly options: ClientOptions = {}) {
+    this.baseUrl = baseUrl.replace(/\/+$/, '');
+  }
+
+  private async request<T>(method: string, path: Array<string | number>, options: RequestOptions = {}): Promise<T> {
+    const query = options.query?.toString();
+    const url = `${this.baseUrl}/api/${path.map((segment) => encodeURIComponent(String(segment))).join('/')}${query ? `?${query}` : ''}`;
+    const headers: Record<string, string> = {};
+    if (options.body !== undefined) headers['Content-Type'] = 'application/json';
+    if (options.version !== undefined) headers['If-Match'] = `"${options.version}"`;
+    if (this.options.bearerToken !== undefined) headers['Authorization'] = `Bearer ${this.options.bearerToken}`;
+    if (this.options.apiKey !== undefined) headers['X-Api-Key'] = this.options.apiKey;
+
+    const response = await (this.options.fetch ?? fetch)(url, {
+      method,
+      headers,
+      body: options.body === undefined ? undefined : JSON.stringify(options.body),
+    });
+    const text = await response.text();
+    if (!response.ok) {
+      throw new ApiError(response.status, errorsOf(text));
+    }
+    return (text ? JSON.parse(text) : undefined) as T;
+  }
+"#;
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+    use serde_json::json;
+
+    use crate::ModelOptions;
+
+    use super::*;
+
+    fn pet() -> InternalModel {
+        let mut id = Property::new(RustDataType::I64);
+        id.read_only = true;
+        let mut name = Property::new(RustDataType::String);
+        name.required = true;
+        name.description = Some("The name".to_string());
+        let mut status = Property::new(RustDataType::String);
+        status.required = true;
+        status.default = Some(json!("available"));
+        status.enum_values = Some(vec![json!("available"), json!("sold")]);
+        let mut tags = Property::new(RustDataType::String);
+        tags.is_array = true;
+        InternalModel {
+            name: "Pet".to_string(),
+            description: None,
+            properties: Some(indexmap! {
+                "id".to_string() => id,
+                "name".to_string() => name,
+                "status".to_string() => status,
+                "photo-urls".to_string() => tags,
+            }),
+            relations: vec![],
+            indexes: vec![],
+            options: ModelOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_interfaces() {
+        let code = create_ts_models(&vec![pet()]);
+        assert!(code.contains(
+            "export interface Pet {\n  id: number | null;\n  /** The name */\n  name: string;\n  status: \"available\" | \"sold\";\n  \"photo-urls\": string[] | null;\n}"
+        ));
+        assert!(code.contains(
+            "export interface CreatePet {\n  /** The name */\n  name: string;\n  status?: \"available\" | \"sold\";\n  \"photo-urls\"?: string[] | null;\n}"
+        ));
+        assert!(code.contains("export type PetField = \"id\" | \"name\" | \"status\";"));
+        assert!(code.contains("export interface ListQuery<F extends string> {"));
+    }
+
+    #[test]
+    fn test_ts_type() {
+        let mut status = Property::new(RustDataType::String);
+        status.enum_values = Some(vec![json!("available"), json!("sold")]);
+        status.is_array = true;
+        assert_eq!("(\"available\" | \"sold\")[]", get_ts_type(&status));
+        assert_eq!("string", get_ts_type(&Property::new(RustDataType::DateTime)));
+        assert_eq!("number", get_ts_type(&Property::new(RustDataType::F32)));
+        assert_eq!("_id", get_ts_key("_id"));
+        assert_eq!("\"1st\"", get_ts_key("1st"));
+    }
+
+    #[test]
+    fn test_client_methods() {
+        let code = create_ts_client(&vec![pet()]);
+        assert!(code.contains("import type { AppError, FilterValue, ListQuery, Page, Pet, PetField, CreatePet, UpdatePet } from './models';"));
+        assert!(code.contains("listPet(query: ListQuery<PetField> = {}): Promise<Page<Pet>> {"));
+        assert!(code.contains("updatePet(id: number, pet: UpdatePet): Promise<Pet> {\n    return this.request('PUT', ['pet', id], { body: pet });"));
+        assert!(code.contains("deletePet(id: number): Promise<void> {"));
+        assert!(!code.contains("searchPet") && !code.contains("upsertPet"));
+
+        let mut model = pet();
+        model.options.versioned = true;
+        model.options.skip_endpoints = vec![Endpoint::List, Endpoint::Create];
+        let code = create_ts_client(&vec![model]);
+        assert!(code.contains("patchPet(id: number, version: number, patch: Partial<UpdatePet>): Promise<Pet> {"));
+        assert!(code.contains("return this.request('DELETE', ['pet', id], { version });"));
+        assert!(!code.contains("Page") && !code.contains("listPet"));
+    }
+}
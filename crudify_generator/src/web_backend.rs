@@ -0,0 +1,662 @@
+/// The web framework the generated server is written for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebFramework {
+    #[default]
+    Axum,
+    ActixWeb,
+}
+
+impl WebFramework {
+    pub(crate) fn backend(self) -> &'static dyn WebBackend {
+        match self {
+            WebFramework::Axum => &Axum,
+            WebFramework::ActixWeb => &ActixWeb,
+        }
+    }
+}
+
+/// A route of the api, `path` has `:id` segments like the paths of the OpenAPI document.
+pub(crate) struct Route {
+    pub(crate) method: &'static str,
+    pub(crate) path: String,
+    pub(crate) handler: String,
+}
+
+impl Route {
+    pub(crate) fn new(method: &'static str, path: &str, handler: &str) -> Route {
+        Route {
+            method,
+            path: path.to_string(),
+            handler: handler.to_string(),
+        }
+    }
+}
+
+/// Renders what the generated server needs from its web framework: the extractors and responses of the handlers, the
+/// routes and the server. The queries and the bodies of the handlers are the same for every framework.
+pub(crate) trait WebBackend {
+    /// The lines of the framework in the `[dependencies]` of the generated `Cargo.toml`.
+    fn dependencies(&self) -> &'static str;
+
+    fn usages(&self) -> &'static str;
+
+    /// Extractor of the `id` path segment, the statement of `path_binding` turns it into a plain `id`.
+    fn path_param(&self, id_type: &str) -> String;
+
+    fn path_binding(&self) -> &'static str;
+
+    /// Extractor of the request headers, and the expression borrowing them.
+    fn headers_param(&self) -> &'static str;
+
+    fn headers(&self) -> &'static str;
+
+    /// Extractor of the state `name` the app was set up with, `owned_state` binds an owned `ty` to the same name.
+    fn state_param(&self, name: &str, ty: &str) -> String;
+
+    fn owned_state(&self, name: &str, ty: &str) -> String;
+
+    fn pool_param(&self) -> String {
+        self.state_param("pool", "DbPool")
+    }
+
+    /// The type of a handler that answers with different statuses, and the conversion of the other responses into it.
+    fn response_type(&self) -> &'static str;
+
+    fn boxed_response(&self, response: &str) -> String;
+
+    /// A `response_type` with a `status`, `200` if there is none, and the JSON `body`.
+    fn response(&self, status: Option<&str>, body: Option<&str>) -> String;
+
+    /// Type and expression of the answer with the row `res`, a versioned row sends its version as `ETag`.
+    fn row_response(&self, versioned: bool) -> (&'static str, &'static str);
+
+    /// The type of a `201 Created`, and the statements and the expression answering with the row `res` at `location`.
+    fn created_type(&self) -> &'static str;
+
+    fn created(&self, location: &str, versioned: bool) -> (String, String);
+
+    /// Type and expression of a `204 No Content`.
+    fn no_content(&self) -> (&'static str, &'static str);
+
+    /// The `created` and `etag` helpers of the responses above.
+    fn created_fn(&self) -> &'static str;
+
+    fn etag_fn(&self) -> &'static str;
+
+    /// Lets handlers take a `Principal`, or an `Option<Principal>` if credentials are optional.
+    fn principal_extractor(&self) -> &'static str;
+
+    /// Sends an `AppError` as JSON with its status.
+    fn error_response(&self) -> &'static str;
+
+    fn openapi_handler(&self) -> &'static str;
+
+    fn docs_handler(&self) -> &'static str;
+
+    /// The framework's `HeaderMap` from an `http::HeaderMap`, like the metadata of a gRPC request.
+    fn headers_from_http(&self, headers: &str) -> String;
+
+    /// `fn app` with the `routes`, the state the handlers extract and the frontend assets.
+    fn app_fn(&self, routes: &[Route], auth: bool, graphql: bool) -> String;
+
+    fn main_attribute(&self) -> &'static str;
+
+    /// The end of `main`, it serves the app on `config.server_addr`, with the docs page if `config.docs` is set.
+    fn serve(&self, auth: bool) -> String;
+}
+
+pub(crate) struct Axum;
+
+impl WebBackend for Axum {
+    fn dependencies(&self) -> &'static str {
+        "axum = { version = \"0.5.16\" }\naxum-extra = { version = \"^0.3\", features = [\"spa\"] }\n"
+    }
+
+    fn usages(&self) -> &'static str {
+        r#"
+use std::{
+    fmt::{Display, Formatter},
+};
+use axum::{
+    extract::{Json, Path, Query},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Extension, Router,
+};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgConnectOptions, Acquire, FromRow};
+use thiserror::Error;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+ "#
+    }
+
+    fn path_param(&self, id_type: &str) -> String {
+        format!("Path(id): Path<{}>, ", id_type)
+    }
+
+    fn path_binding(&self) -> &'static str {
+        ""
+    }
+
+    fn headers_param(&self) -> &'static str {
+        "headers: HeaderMap, "
+    }
+
+    fn headers(&self) -> &'static str {
+        "&headers"
+    }
+
+    fn state_param(&self, name: &str, ty: &str) -> String {
+        format!("Extension({}): Extension<{}>", name, ty)
+    }
+
+    fn owned_state(&self, _name: &str, _ty: &str) -> String {
+        "".to_string()
+    }
+
+    fn response_type(&self) -> &'static str {
+        "Response"
+    }
+
+    fn boxed_response(&self, response: &str) -> String {
+        format!("{}.into_response()", response)
+    }
+
+    fn response(&self, status: Option<&str>, body: Option<&str>) -> String {
+        match (status, body) {
+            (Some(status), Some(body)) => format!("({}, Json({})).into_response()", status, body),
+            (None, Some(body)) => format!("Json({}).into_response()", body),
+            (Some(status), None) => format!("{}.into_response()", status),
+            (None, None) => "StatusCode::OK.into_response()".to_string(),
+        }
+    }
+
+    fn row_response(&self, versioned: bool) -> (&'static str, &'static str) {
+        if versioned {
+            ("(HeaderMap, Json<Value>)", "(etag(res.version), Json(json!(res)))")
+        } else {
+            ("Json<Value>", "Json(json!(res))")
+        }
+    }
+
+    fn created_type(&self) -> &'static str {
+        "(StatusCode, HeaderMap, Json<Value>)"
+    }
+
+    fn created(&self, location: &str, versioned: bool) -> (String, String) {
+        let created = format!("created({}, json!(res))", location);
+        if versioned {
+            (format!("let mut response = {};\nresponse.1.extend(etag(res.version));\n", created), "response".to_string())
+        } else {
+            ("".to_string(), created)
+        }
+    }
+
+    fn no_content(&self) -> (&'static str, &'static str) {
+        ("StatusCode", "StatusCode::NO_CONTENT")
+    }
+
+    fn created_fn(&self) -> &'static str {
+        r#"
+fn created(location: Option<String>, body: Value) -> (StatusCode, HeaderMap, Json<Value>) {
+    let mut headers = HeaderMap::new();
+    if let Some(location) = location.and_then(|location| HeaderValue::from_str(&location).ok()) {
+        headers.insert(header::LOCATION, location);
+    }
+    (StatusCode::CREATED, headers, Json(body))
+}
+"#
+    }
+
+    fn etag_fn(&self) -> &'static str {
+        r#"
+fn etag(version: i64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, HeaderValue::from_str(&format!("\"{}\"", version)).expect("An ETag is a valid header"));
+    headers
+}
+"#
+    }
+
+    fn principal_extractor(&self) -> &'static str {
+        r#"
+#[axum::async_trait]
+impl<B: Send> axum::extract::FromRequest<B> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request(req: &mut axum::extract::RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Extension(authenticator) = Extension::<std::sync::Arc<Authenticator>>::from_request(req)
+            .await
+            .map_err(|e| AppError::new_internal(e.to_string()))?;
+        authenticator.authenticate(req.headers())
+    }
+}
+"#
+    }
+
+    fn error_response(&self) -> &'static str {
+        r#"
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        if self.status_code == StatusCode::UNAUTHORIZED.as_u16() {
+            headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        (
+            StatusCode::from_u16(self.status_code).unwrap(),
+            headers,
+            serde_json::to_string(&self).unwrap(),
+        )
+            .into_response()
+    }
+}
+    "#
+    }
+
+    fn openapi_handler(&self) -> &'static str {
+        r#"
+async fn get_openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], include_str!("../openapi.json"))
+}
+"#
+    }
+
+    fn docs_handler(&self) -> &'static str {
+        r#"
+async fn get_docs() -> axum::response::Html<&'static str> {
+    axum::response::Html(DOCS_PAGE)
+}
+"#
+    }
+
+    fn headers_from_http(&self, headers: &str) -> String {
+        headers.to_string()
+    }
+
+    fn app_fn(&self, routes: &[Route], auth: bool, graphql: bool) -> String {
+        let mut code = if auth {
+            "\nfn app(pool: DbPool, authenticator: std::sync::Arc<Authenticator>) -> Router {\n    Router::new()\n"
+        } else {
+            "\nfn app(pool: DbPool) -> Router {\n    Router::new()\n"
+        }
+        .to_string();
+        for route in routes {
+            code.push_str(&format!(".route(\"{}\", {}({}))\n", route.path, route.method, route.handler));
+        }
+        code.push_str(r#".merge(axum_extra::routing::SpaRouter::new("/assets", "../dist"))"#);
+        if graphql {
+            code.push_str("\n    .layer(Extension(graphql_schema()))");
+        }
+        if auth {
+            code.push_str("\n    .layer(Extension(authenticator))");
+        }
+        code.push_str("\n    .layer(Extension(pool))\n}");
+
+        code
+    }
+
+    fn main_attribute(&self) -> &'static str {
+        "#[tokio::main]"
+    }
+
+    fn serve(&self, auth: bool) -> String {
+        format!(
+            r#"    let mut app = app(pool{});
+    if config.docs {{
+        app = app.route("/api/docs", get(get_docs));
+    }}
+    axum::Server::bind(&config.server_addr.parse().expect("Expected a parsable address to start a server on"))
+        .serve(app.into_make_service())
+        .await
+        .expect("Could not start server");
+    Ok(())"#,
+            if auth { ", authenticator" } else { "" }
+        )
+    }
+}
+
+pub(crate) struct ActixWeb;
+
+impl WebBackend for ActixWeb {
+    fn dependencies(&self) -> &'static str {
+        "actix-web = \"4\"\nactix-files = \"0.6\"\n"
+    }
+
+    fn usages(&self) -> &'static str {
+        r#"
+use std::{
+    fmt::{Display, Formatter},
+};
+use actix_web::{
+    http::{header, header::HeaderMap, StatusCode},
+    web::{self, Json, Query},
+    App, HttpRequest, HttpResponse, HttpServer,
+};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgConnectOptions, Acquire, FromRow};
+use thiserror::Error;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+ "#
+    }
+
+    fn path_param(&self, id_type: &str) -> String {
+        format!("id: web::Path<{}>, ", id_type)
+    }
+
+    fn path_binding(&self) -> &'static str {
+        "let id = id.into_inner();\n"
+    }
+
+    fn headers_param(&self) -> &'static str {
+        "req: HttpRequest, "
+    }
+
+    fn headers(&self) -> &'static str {
+        "req.headers()"
+    }
+
+    fn state_param(&self, name: &str, ty: &str) -> String {
+        format!("{}: web::Data<{}>", name, ty)
+    }
+
+    fn owned_state(&self, name: &str, ty: &str) -> String {
+        format!("let {0} = {1}::clone(&{0});\n", name, ty)
+    }
+
+    fn response_type(&self) -> &'static str {
+        "HttpResponse"
+    }
+
+    fn boxed_response(&self, response: &str) -> String {
+        response.to_string()
+    }
+
+    fn response(&self, status: Option<&str>, body: Option<&str>) -> String {
+        match (status, body) {
+            (Some(status), Some(body)) => format!("HttpResponse::build({}).json({})", status, body),
+            (None, Some(body)) => format!("HttpResponse::Ok().json({})", body),
+            (Some(status), None) => format!("HttpResponse::new({})", status),
+            (None, None) => "HttpResponse::Ok().finish()".to_string(),
+        }
+    }
+
+    fn row_response(&self, versioned: bool) -> (&'static str, &'static str) {
+        if versioned {
+            ("HttpResponse", "HttpResponse::Ok().insert_header(etag(res.version)).json(json!(res))")
+        } else {
+            ("HttpResponse", "HttpResponse::Ok().json(json!(res))")
+        }
+    }
+
+    fn created_type(&self) -> &'static str {
+        "HttpResponse"
+    }
+
+    fn created(&self, location: &str, versioned: bool) -> (String, String) {
+        let etag = if versioned { ".insert_header(etag(res.version))" } else { "" };
+        ("".to_string(), format!("created({}){}.json(json!(res))", location, etag))
+    }
+
+    fn no_content(&self) -> (&'static str, &'static str) {
+        ("HttpResponse", "HttpResponse::NoContent().finish()")
+    }
+
+    fn created_fn(&self) -> &'static str {
+        r#"
+fn created(location: Option<String>) -> actix_web::HttpResponseBuilder {
+    let mut response = HttpResponse::Created();
+    if let Some(location) = location {
+        response.insert_header((header::LOCATION, location));
+    }
+    response
+}
+"#
+    }
+
+    fn etag_fn(&self) -> &'static str {
+        r#"
+fn etag(version: i64) -> (header::HeaderName, String) {
+    (header::ETAG, format!("\"{}\"", version))
+}
+"#
+    }
+
+    fn principal_extractor(&self) -> &'static str {
+        r#"
+impl actix_web::FromRequest for Principal {
+    type Error = AppError;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        std::future::ready(match req.app_data::<web::Data<Authenticator>>() {
+            Some(authenticator) => authenticator.authenticate(req.headers()),
+            None => Err(AppError::new_internal("The app has no authenticator".to_string())),
+        })
+    }
+}
+"#
+    }
+
+    fn error_response(&self) -> &'static str {
+        r#"
+impl actix_web::ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(actix_web::ResponseError::status_code(self));
+        if self.status_code == StatusCode::UNAUTHORIZED.as_u16() {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.json(self)
+    }
+}
+"#
+    }
+
+    fn openapi_handler(&self) -> &'static str {
+        r#"
+async fn get_openapi() -> HttpResponse {
+    HttpResponse::Ok().content_type("application/json").body(include_str!("../openapi.json"))
+}
+"#
+    }
+
+    fn docs_handler(&self) -> &'static str {
+        r#"
+async fn get_docs() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(DOCS_PAGE)
+}
+"#
+    }
+
+    fn headers_from_http(&self, headers: &str) -> String {
+        format!("HeaderMap::from({})", headers)
+    }
+
+    /// actix-web answers a path with the first resource that matches it, so all methods of a path are one resource and
+    /// fixed paths like `/api/pet/bulk` come before `/api/pet/{id}`.
+    fn app_fn(&self, routes: &[Route], auth: bool, graphql: bool) -> String {
+        let mut resources: Vec<(String, Vec<&Route>)> = vec![];
+        for route in routes {
+            let path = route
+                .path
+                .split('/')
+                .map(|segment| match segment.strip_prefix(':') {
+                    Some(param) => format!("{{{}}}", param),
+                    None => segment.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            match resources.iter_mut().find(|(resource, _)| *resource == path) {
+                Some((_, routes)) => routes.push(route),
+                None => resources.push((path, vec![route])),
+            }
+        }
+        resources.sort_by_key(|(path, _)| path.contains('{'));
+
+        let mut code = if auth {
+            "\nfn app(cfg: &mut web::ServiceConfig, pool: DbPool, authenticator: std::sync::Arc<Authenticator>) {\n    cfg.app_data(web::Data::new(pool))\n"
+        } else {
+            "\nfn app(cfg: &mut web::ServiceConfig, pool: DbPool) {\n    cfg.app_data(web::Data::new(pool))\n"
+        }
+        .to_string();
+        if auth {
+            code.push_str(".app_data(web::Data::from(authenticator))\n");
+        }
+        if graphql {
+            code.push_str(".app_data(web::Data::new(graphql_schema()))\n");
+        }
+        for (path, routes) in resources {
+            let routes: String = routes
+                .iter()
+                .map(|route| format!(".route(web::{}().to({}))", route.method, route.handler))
+                .collect();
+            code.push_str(&format!(".service(web::resource(\"{}\"){})\n", path, routes));
+        }
+        code.push_str(".service(actix_files::Files::new(\"/assets\", \"../dist\").index_file(\"index.html\"));\n}");
+
+        code
+    }
+
+    fn main_attribute(&self) -> &'static str {
+        "#[actix_web::main]"
+    }
+
+    fn serve(&self, auth: bool) -> String {
+        format!(
+            r#"    let docs = config.docs;
+    HttpServer::new(move || {{
+        App::new().configure(|cfg| {{
+            app(cfg, pool.clone(){});
+            if docs {{
+                cfg.route("/api/docs", web::get().to(get_docs));
+            }}
+        }})
+    }})
+    .bind(config.server_addr.as_str())
+    .expect("Expected a parsable address to start a server on")
+    .run()
+    .await
+    .expect("Could not start server");
+    Ok(())"#,
+            if auth { ", authenticator.clone()" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes() -> Vec<Route> {
+        vec![
+            Route::new("get", "/api/pet", "get_pets"),
+            Route::new("post", "/api/pet", "create_pet"),
+            Route::new("get", "/api/pet/:id", "get_pet"),
+            Route::new("delete", "/api/pet/:id", "delete_pet"),
+            Route::new("post", "/api/pet/bulk", "create_pets"),
+        ]
+    }
+
+    #[test]
+    fn test_framework_backend() {
+        assert_eq!("#[tokio::main]", WebFramework::Axum.backend().main_attribute());
+        assert_eq!("#[actix_web::main]", WebFramework::ActixWeb.backend().main_attribute());
+    }
+
+    #[test]
+    fn test_axum_extractors() {
+        assert_eq!("Path(id): Path<i64>, ", Axum.path_param("i64"));
+        assert_eq!("", Axum.path_binding());
+        assert_eq!("headers: HeaderMap, ", Axum.headers_param());
+        assert_eq!("&headers", Axum.headers());
+        assert_eq!("Extension(pool): Extension<DbPool>", Axum.pool_param());
+        assert_eq!("", Axum.owned_state("pool", "DbPool"));
+        assert_eq!("metadata", Axum.headers_from_http("metadata"));
+        assert!(Axum.principal_extractor().contains("impl<B: Send> axum::extract::FromRequest<B> for Principal {"));
+    }
+
+    #[test]
+    fn test_axum_responses() {
+        assert_eq!("(StatusCode::CONFLICT, Json(body)).into_response()", Axum.response(Some("StatusCode::CONFLICT"), Some("body")));
+        assert_eq!("Json(body).into_response()", Axum.response(None, Some("body")));
+        assert_eq!("StatusCode::NOT_MODIFIED.into_response()", Axum.response(Some("StatusCode::NOT_MODIFIED"), None));
+        assert_eq!("StatusCode::OK.into_response()", Axum.response(None, None));
+        assert_eq!("res.into_response()", Axum.boxed_response("res"));
+        assert_eq!(("(HeaderMap, Json<Value>)", "(etag(res.version), Json(json!(res)))"), Axum.row_response(true));
+        assert_eq!(("Json<Value>", "Json(json!(res))"), Axum.row_response(false));
+        assert_eq!(("".to_string(), "created(location, json!(res))".to_string()), Axum.created("location", false));
+        assert_eq!(
+            "let mut response = created(location, json!(res));\nresponse.1.extend(etag(res.version));\n",
+            Axum.created("location", true).0
+        );
+        assert_eq!(("StatusCode", "StatusCode::NO_CONTENT"), Axum.no_content());
+        assert!(Axum.error_response().contains("impl IntoResponse for AppError {"));
+    }
+
+    #[test]
+    fn test_axum_app() {
+        let code = Axum.app_fn(&routes(), true, true);
+        assert!(code.contains("fn app(pool: DbPool, authenticator: std::sync::Arc<Authenticator>) -> Router {"));
+        assert!(code.contains(".route(\"/api/pet\", get(get_pets))\n.route(\"/api/pet\", post(create_pet))\n"));
+        assert!(code.contains(".route(\"/api/pet/:id\", delete(delete_pet))\n"));
+        assert!(code.contains("\n    .layer(Extension(graphql_schema()))\n    .layer(Extension(authenticator))\n    .layer(Extension(pool))\n}"));
+        assert!(!Axum.app_fn(&routes(), false, false).contains("authenticator"));
+        assert!(Axum.serve(true).contains("let mut app = app(pool, authenticator);"));
+    }
+
+    #[test]
+    fn test_actix_web_extractors() {
+        assert_eq!("id: web::Path<i64>, ", ActixWeb.path_param("i64"));
+        assert_eq!("let id = id.into_inner();\n", ActixWeb.path_binding());
+        assert_eq!("req: HttpRequest, ", ActixWeb.headers_param());
+        assert_eq!("req.headers()", ActixWeb.headers());
+        assert_eq!("pool: web::Data<DbPool>", ActixWeb.pool_param());
+        assert_eq!("let pool = DbPool::clone(&pool);\n", ActixWeb.owned_state("pool", "DbPool"));
+        assert_eq!("HeaderMap::from(metadata)", ActixWeb.headers_from_http("metadata"));
+        assert!(ActixWeb.principal_extractor().contains("impl actix_web::FromRequest for Principal {"));
+    }
+
+    #[test]
+    fn test_actix_web_responses() {
+        assert_eq!(
+            "HttpResponse::build(StatusCode::CONFLICT).json(body)",
+            ActixWeb.response(Some("StatusCode::CONFLICT"), Some("body"))
+        );
+        assert_eq!("HttpResponse::Ok().json(body)", ActixWeb.response(None, Some("body")));
+        assert_eq!("HttpResponse::new(StatusCode::NOT_MODIFIED)", ActixWeb.response(Some("StatusCode::NOT_MODIFIED"), None));
+        assert_eq!("HttpResponse::Ok().finish()", ActixWeb.response(None, None));
+        assert_eq!("res", ActixWeb.boxed_response("res"));
+        assert_eq!(
+            ("HttpResponse", "HttpResponse::Ok().insert_header(etag(res.version)).json(json!(res))"),
+            ActixWeb.row_response(true)
+        );
+        assert_eq!(
+            ("".to_string(), "created(location).insert_header(etag(res.version)).json(json!(res))".to_string()),
+            ActixWeb.created("location", true)
+        );
+        assert_eq!(("HttpResponse", "HttpResponse::NoContent().finish()"), ActixWeb.no_content());
+        assert!(ActixWeb.error_response().contains("impl actix_web::ResponseError for AppError {"));
+    }
+
+    #[test]
+    fn test_actix_web_app() {
+        let code = ActixWeb.app_fn(&routes(), true, true);
+        assert!(code.contains("fn app(cfg: &mut web::ServiceConfig, pool: DbPool, authenticator: std::sync::Arc<Authenticator>) {"));
+        assert!(code.contains(".app_data(web::Data::from(authenticator))\n.app_data(web::Data::new(graphql_schema()))\n"));
+        assert!(code.contains(
+            ".service(web::resource(\"/api/pet\").route(web::get().to(get_pets)).route(web::post().to(create_pet)))\n\
+             .service(web::resource(\"/api/pet/bulk\").route(web::post().to(create_pets)))\n\
+             .service(web::resource(\"/api/pet/{id}\").route(web::get().to(get_pet)).route(web::delete().to(delete_pet)))\n"
+        ));
+        assert!(!ActixWeb.app_fn(&routes(), false, false).contains("authenticator"));
+        assert!(ActixWeb.serve(true).contains("app(cfg, pool.clone(), authenticator.clone());"));
+    }
+}